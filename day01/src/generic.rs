@@ -0,0 +1,124 @@
+use std::cmp::Ordering;
+
+use super::{check_window, WindowError};
+
+/// What to do when two values cannot be compared, like when one of them is NaN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Incomparable {
+    /// Ignore the comparison and keep counting.
+    Skip,
+    /// Values that cannot be compared with themselves split the input and the windows that
+    /// contain any of them are not compared.
+    Break,
+    /// Stop counting and return an error.
+    Error,
+}
+
+/// Error returned by the generic versions of `count_increases`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompareError {
+    Window(WindowError),
+    /// The values at `first` and `second` could not be compared.
+    Incomparable {
+        first: usize,
+        second: usize,
+    },
+}
+
+impl From<WindowError> for CompareError {
+    fn from(err: WindowError) -> Self {
+        Self::Window(err)
+    }
+}
+
+impl std::fmt::Display for CompareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Window(err) => err.fmt(f),
+            Self::Incomparable { first, second } => write!(
+                f,
+                "the values at indices {} and {} cannot be compared",
+                first, second
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompareError {}
+
+/// Same as `count_increases_by` but the values are compared with `compare`, which returns `None`
+/// for values that cannot be compared.
+pub fn count_increases_with<T, F>(
+    slice: &[T],
+    window: usize,
+    mut compare: F,
+    policy: Incomparable,
+) -> Result<usize, CompareError>
+where
+    F: FnMut(&T, &T) -> Option<Ordering>,
+{
+    check_window(window, slice.len())?;
+
+    let mut count = 0;
+    // The index of the last value that cannot be compared with itself. Only used when breaking.
+    let mut last_gap = None;
+
+    for (j, value) in slice.iter().enumerate() {
+        if policy == Incomparable::Break && compare(value, value).is_none() {
+            last_gap = Some(j);
+        }
+
+        // `j` is the last index of the current window, so there is nothing to compare yet.
+        let Some(i) = j.checked_sub(window) else {
+            continue;
+        };
+
+        // Skip the windows that contain a gap.
+        if last_gap.is_some_and(|gap| gap >= i) {
+            continue;
+        }
+
+        match compare(value, &slice[i]) {
+            Some(Ordering::Greater) => count += 1,
+            Some(_) => {}
+            None => match policy {
+                Incomparable::Skip | Incomparable::Break => {}
+                Incomparable::Error => {
+                    return Err(CompareError::Incomparable {
+                        first: i,
+                        second: j,
+                    })
+                }
+            },
+        }
+    }
+
+    Ok(count)
+}
+
+/// Same as `count_increases_with` but the values are compared using the key returned by `key`.
+pub fn count_increases_by_key<T, K, F>(
+    slice: &[T],
+    window: usize,
+    mut key: F,
+    policy: Incomparable,
+) -> Result<usize, CompareError>
+where
+    K: PartialOrd,
+    F: FnMut(&T) -> K,
+{
+    count_increases_with(slice, window, |a, b| key(a).partial_cmp(&key(b)), policy)
+}
+
+/// Same as `count_increases_with` but the values are compared using their `PartialOrd`
+/// implementation.
+pub fn count_increases_partial<T>(
+    slice: &[T],
+    window: usize,
+    policy: Incomparable,
+) -> Result<usize, CompareError>
+where
+    T: PartialOrd,
+{
+    count_increases_with(slice, window, T::partial_cmp, policy)
+}
@@ -1,23 +1,323 @@
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
+use generic::{count_increases_by_key, count_increases_partial, Incomparable};
+use parallel::{count_increases_chunked, count_increases_parallel};
+use parse::{parse_input, parse_input_with, parse_lines, parse_record, LineError, ParseErrors};
+use report::DepthReport;
 
-use std::fs::read_to_string;
+use std::{
+    collections::VecDeque,
+    fs::read_to_string,
+    io::{read_to_string as read_all, stdin},
+    num::NonZeroUsize,
+    thread::available_parallelism,
+    time::Instant,
+};
 
+mod generic;
+mod parallel;
+mod parse;
+mod report;
+
+/// Error returned when the sliding window does not fit in the input.
+#[derive(Debug, PartialEq, Eq)]
+struct WindowError {
+    window: usize,
+    len: usize,
+}
+
+impl std::fmt::Display for WindowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "window of size {} does not fit in an input of length {}",
+            self.window, self.len
+        )
+    }
+}
+
+impl std::error::Error for WindowError {}
+
+/// Check that a window of size `window` fits in a slice of length `len`.
+fn check_window(window: usize, len: usize) -> Result<(), WindowError> {
+    if window >= len {
+        return Err(WindowError { window, len });
+    }
+
+    Ok(())
+}
+
+/// Count how many times the sum of a window of size `N` is larger than the previous one.
+///
+/// Two consecutive windows share all their elements except the first element of the previous
+/// window and the last element of the current one, so it is enough to compare those.
 #[inline(never)]
-fn count_increases<const N: usize>(slice: &[u32]) -> usize {
-    (0..slice.len() - N)
+fn count_increases<const N: usize>(slice: &[u32]) -> Result<usize, WindowError> {
+    check_window(N, slice.len())?;
+
+    Ok((0..slice.len() - N)
         .filter(|&i| slice[i + N] > slice[i])
-        .count()
+        .count())
+}
+
+/// Same as `count_increases` but the window size is chosen at runtime.
+#[inline(never)]
+fn count_increases_by(slice: &[u32], window: usize) -> Result<usize, WindowError> {
+    check_window(window, slice.len())?;
+
+    Ok((0..slice.len() - window)
+        .filter(|&i| slice[i + window] > slice[i])
+        .count())
+}
+
+/// Streaming version of `count_increases` that only keeps the last `window` values in memory.
+struct IncreaseCounter {
+    /// The last `window` values that were pushed, oldest first.
+    ring: VecDeque<u32>,
+    window: usize,
+    /// The number of values pushed so far.
+    seen: usize,
+    /// The number of increases found so far.
+    count: usize,
+}
+
+impl IncreaseCounter {
+    fn new(window: usize) -> Self {
+        Self {
+            ring: VecDeque::with_capacity(window),
+            window,
+            seen: 0,
+            count: 0,
+        }
+    }
+
+    /// Push the next value of the sequence.
+    fn push(&mut self, value: u32) {
+        self.seen += 1;
+
+        // A window of size zero compares each value with itself so it never increases.
+        if self.window == 0 {
+            return;
+        }
+
+        // Once the buffer is full, the oldest value is the one `window` positions behind `value`.
+        if self.ring.len() == self.window {
+            if let Some(oldest) = self.ring.pop_front() {
+                if value > oldest {
+                    self.count += 1;
+                }
+            }
+        }
+
+        self.ring.push_back(value);
+    }
+
+    /// Return the number of increases, or an error if the window never fit in the sequence.
+    fn finish(&self) -> Result<usize, WindowError> {
+        check_window(self.window, self.seen)?;
+
+        Ok(self.count)
+    }
+}
+
+/// Same as `count_increases_by` but consumes the values from an iterator.
+fn count_increases_stream<I>(values: I, window: usize) -> Result<usize, WindowError>
+where
+    I: IntoIterator<Item = u32>,
+{
+    let mut counter = IncreaseCounter::new(window);
+
+    for value in values {
+        counter.push(value);
+    }
+
+    counter.finish()
+}
+
+/// What the program should print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// The number of increases for each part or for the given window.
+    Answers,
+    /// The depth report as a table.
+    Report,
+    /// The depth report as JSON.
+    Json,
+    /// The time taken by each implementation of `count_increases`.
+    Bench,
+}
+
+/// Time `count` on `input` for the given window and print the average duration.
+fn bench(
+    name: &str,
+    input: &[u32],
+    window: usize,
+    count: impl Fn(&[u32], usize) -> Result<usize, WindowError>,
+) -> Result<usize, WindowError> {
+    const ITERATIONS: u32 = 100;
+
+    let start = Instant::now();
+    let mut result = Ok(0);
+    for _ in 0..ITERATIONS {
+        result = count(std::hint::black_box(input), window);
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+
+    println!("{:<10} window {}: {:>12?}", name, window, elapsed);
+
+    result
 }
 
 fn main() -> Result<(), Error> {
-    let input = read_to_string("./input")
-        .context("could not read input file")?
-        .lines()
-        .map(|line| line.parse().context("could not parse input line"))
-        .collect::<Result<Vec<_>, _>>()?;
+    let mut window = None;
+    let mut from_stdin = false;
+    let mut mode = Mode::Answers;
+    let mut nan_policy = None;
+    let mut all_errors = false;
+    let mut records = false;
+
+    // The input is read from stdin if `-` is passed, `--report`, `--json` and `--bench` print
+    // something else instead of the answers and an optional window size can be passed too.
+    // `--nan=<skip|break|error>` parses the input as floats and handles NaN with that policy.
+    // `--all-errors` reports every line that cannot be parsed instead of only the first one.
+    // `--records` parses each line as a timestamp followed by a depth.
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "-" => from_stdin = true,
+            "--report" => mode = Mode::Report,
+            "--json" => mode = Mode::Json,
+            "--bench" => mode = Mode::Bench,
+            "--all-errors" => all_errors = true,
+            "--records" => records = true,
+            "--nan=skip" => nan_policy = Some(Incomparable::Skip),
+            "--nan=break" => nan_policy = Some(Incomparable::Break),
+            "--nan=error" => nan_policy = Some(Incomparable::Error),
+            _ if arg.starts_with("--nan=") => bail!("unknown NaN policy {:?}", arg),
+            _ => window = Some(arg.parse().context("could not parse window size")?),
+        }
+    }
+
+    if from_stdin && mode == Mode::Answers && nan_policy.is_none() && !records {
+        // Stop at the first error and keep it to report it later. If every error must be
+        // reported, the lines that cannot be parsed are collected and skipped instead.
+        let mut error = Ok(());
+        let mut line_errors = Vec::new();
+        let values = parse_lines(stdin().lock())
+            .map_while(|value| match value {
+                Ok(value) => Some(Some(value)),
+                Err(err) => match err.downcast::<LineError>() {
+                    Ok(err) if all_errors => {
+                        line_errors.push(err);
+                        Some(None)
+                    }
+                    Ok(err) => {
+                        error = Err(err.into());
+                        None
+                    }
+                    Err(err) => {
+                        error = Err(err);
+                        None
+                    }
+                },
+            })
+            .flatten();
+
+        if let Some(window) = window {
+            let count = count_increases_stream(values, window);
+            error?;
+            if !line_errors.is_empty() {
+                return Err(ParseErrors(line_errors).into());
+            }
+            println!("Window {}: {}", window, count?);
+        } else {
+            // Stream both parts at the same time so the input is only read once.
+            let (mut part1, mut part2) = (IncreaseCounter::new(1), IncreaseCounter::new(3));
+            for value in values {
+                part1.push(value);
+                part2.push(value);
+            }
+            error?;
+            if !line_errors.is_empty() {
+                return Err(ParseErrors(line_errors).into());
+            }
+            println!("Part 1: {}", part1.finish()?);
+            println!("Part 2: {}", part2.finish()?);
+        }
+
+        return Ok(());
+    }
 
-    println!("Part 1: {}", count_increases::<1>(&input));
-    println!("Part 2: {}", count_increases::<3>(&input));
+    let input = if from_stdin {
+        read_all(stdin()).context("could not read stdin")?
+    } else {
+        read_to_string("./input").context("could not read input file")?
+    };
+
+    if records {
+        let records = parse_input_with(&input, all_errors, parse_record)?;
+
+        // Depths can always be compared so the policy does not matter.
+        let count = |window| {
+            count_increases_by_key(&records, window, |&(_, depth)| depth, Incomparable::Error)
+        };
+
+        if let Some(window) = window {
+            println!("Window {}: {}", window, count(window)?);
+        } else {
+            println!("Part 1: {}", count(1)?);
+            println!("Part 2: {}", count(3)?);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(policy) = nan_policy {
+        let input = parse_input::<f32>(&input, all_errors)?;
+
+        if let Some(window) = window {
+            let count = count_increases_partial(&input, window, policy)?;
+            println!("Window {}: {}", window, count);
+        } else {
+            println!("Part 1: {}", count_increases_partial(&input, 1, policy)?);
+            println!("Part 2: {}", count_increases_partial(&input, 3, policy)?);
+        }
+
+        return Ok(());
+    }
+
+    let input = parse_input(&input, all_errors)?;
+
+    match mode {
+        Mode::Answers => {}
+        Mode::Report => {
+            print!("{}", DepthReport::new(&input));
+            return Ok(());
+        }
+        Mode::Json => {
+            println!("{}", DepthReport::new(&input).to_json());
+            return Ok(());
+        }
+        Mode::Bench => {
+            let threads = available_parallelism().unwrap_or(NonZeroUsize::MIN);
+
+            for window in window.map_or(vec![1, 3], |window| vec![window]) {
+                let scalar = bench("scalar", &input, window, count_increases_by)?;
+                let chunked = bench("chunked", &input, window, count_increases_chunked)?;
+                let parallel = bench("parallel", &input, window, |input, window| {
+                    count_increases_parallel(input, window, threads)
+                })?;
+                assert_eq!(scalar, chunked);
+                assert_eq!(scalar, parallel);
+            }
+            return Ok(());
+        }
+    }
+
+    if let Some(window) = window {
+        println!("Window {}: {}", window, count_increases_by(&input, window)?);
+    } else {
+        println!("Part 1: {}", count_increases::<1>(&input)?);
+        println!("Part 2: {}", count_increases::<3>(&input)?);
+    }
 
     Ok(())
 }
@@ -30,13 +330,213 @@ mod tests {
     fn test1() {
         let input = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
 
-        assert_eq!(7, count_increases::<1>(&input));
+        assert_eq!(Ok(7), count_increases::<1>(&input));
+        assert_eq!(Ok(7), count_increases_by(&input, 1));
     }
 
     #[test]
     fn test2() {
         let input = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
 
-        assert_eq!(5, count_increases::<3>(&input));
+        assert_eq!(Ok(5), count_increases::<3>(&input));
+        assert_eq!(Ok(5), count_increases_by(&input, 3));
+    }
+
+    #[test]
+    fn stream() {
+        let input = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+
+        for window in 0..input.len() {
+            assert_eq!(
+                count_increases_by(&input, window),
+                count_increases_stream(input, window)
+            );
+        }
+        assert_eq!(
+            count_increases::<3>(&input),
+            count_increases_stream(input, 3)
+        );
+        assert_eq!(
+            Err(WindowError {
+                window: 10,
+                len: 10
+            }),
+            count_increases_stream(input, 10)
+        );
+
+        let reader = "199\n200\n208\n210\n200\n207\n240\n269\n260\n263\n".as_bytes();
+        let values = parse_lines(reader).map(Result::unwrap);
+        assert_eq!(Ok(5), count_increases_stream(values, 3));
+    }
+
+    #[test]
+    fn report() {
+        let input = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+        let report = DepthReport::new(&input);
+
+        assert_eq!(count_increases::<1>(&input), Ok(report.increases));
+        assert_eq!(2, report.decreases);
+        assert_eq!(0, report.plateaus);
+        assert_eq!(
+            Some(report::Run { start: 0, end: 3 }),
+            report.longest_increasing_run
+        );
+        assert_eq!(
+            Some(report::Jump {
+                index: 5,
+                delta: 33
+            }),
+            report.largest_jump
+        );
+        assert_eq!(Some(&1), report.delta_histogram.get(&-10));
+        assert_eq!(
+            "{\"increases\":7,\"decreases\":2,\"plateaus\":0,\"longest_increasing_run\":{\"start\":0,\"end\":3,\"len\":4},\"largest_jump\":{\"index\":5,\"delta\":33},\"delta_histogram\":{\"-10\":1,\"-9\":1,\"1\":1,\"2\":1,\"3\":1,\"7\":1,\"8\":1,\"29\":1,\"33\":1}}",
+            report.to_json()
+        );
+    }
+
+    #[test]
+    fn chunked_and_parallel() {
+        // Pseudo-random input long enough to fill several chunks and leave a remainder.
+        let mut state = 12345u32;
+        let input = (0..1003)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                state >> 16
+            })
+            .collect::<Vec<_>>();
+
+        for window in [0, 1, 3, 8, 17, 1002, 1003] {
+            let expected = count_increases_by(&input, window);
+            assert_eq!(expected, count_increases_chunked(&input, window));
+            for threads in [1, 2, 3, 7, 64, 2000] {
+                let threads = NonZeroUsize::new(threads).unwrap();
+                assert_eq!(expected, count_increases_parallel(&input, window, threads));
+            }
+        }
+    }
+
+    #[test]
+    fn generic() {
+        use generic::{count_increases_with, CompareError};
+
+        let input = [
+            199.0, 200.0, 208.0, 210.0, 200.0, 207.0, 240.0, 269.0, 260.0, 263.0,
+        ];
+        assert_eq!(
+            Ok(7),
+            count_increases_partial(&input, 1, Incomparable::Error)
+        );
+        assert_eq!(
+            Ok(5),
+            count_increases_partial(&input, 3, Incomparable::Error)
+        );
+
+        let input = [199.0, 200.0, f32::NAN, 210.0, 200.0, 207.0, 240.0];
+        assert_eq!(
+            Ok(3),
+            count_increases_partial(&input, 1, Incomparable::Skip)
+        );
+        assert_eq!(
+            Ok(2),
+            count_increases_partial(&input, 2, Incomparable::Skip)
+        );
+        // Comparing 200.0 with 210.0 is skipped too because the window between them has a NaN.
+        assert_eq!(
+            Ok(1),
+            count_increases_partial(&input, 2, Incomparable::Break)
+        );
+        assert_eq!(
+            Err(CompareError::Incomparable {
+                first: 1,
+                second: 2
+            }),
+            count_increases_partial(&input, 1, Incomparable::Error)
+        );
+        assert_eq!(
+            "the values at indices 0 and 2 cannot be compared",
+            count_increases_partial(&input, 2, Incomparable::Error)
+                .unwrap_err()
+                .to_string()
+        );
+
+        // Values do not need to be `Copy`.
+        let input = ["a", "c", "b", "d"].map(String::from);
+        assert_eq!(
+            Ok(2),
+            count_increases_partial(&input, 1, Incomparable::Error)
+        );
+
+        // Decreases can be counted by reversing the comparison.
+        assert_eq!(
+            Ok(1),
+            count_increases_with(&input, 1, |a, b| b.partial_cmp(a), Incomparable::Skip)
+        );
+
+        let records = [(10u64, 199u32), (20, 200), (30, 208), (40, 210), (50, 200)];
+        assert_eq!(
+            Ok(3),
+            count_increases_by_key(&records, 1, |&(_, depth)| depth, Incomparable::Error)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Ok(vec![199, 200]), parse_input::<u32>("199\n200", false));
+
+        let input = "199\n2x0\n\n99999999999\n-3";
+        let errors = parse_input::<u32>(input, true).unwrap_err().0;
+        let locations = errors
+            .iter()
+            .map(|err| (err.line, err.column, err.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (2, 2, "2x0"),
+                (3, 1, ""),
+                (4, 10, "99999999999"),
+                (5, 1, "-3")
+            ],
+            locations
+        );
+
+        let errors = parse_input::<u32>(input, false).unwrap_err().0;
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "line 2, column 2: could not parse \"2x0\": invalid digit found in string",
+            errors[0].to_string()
+        );
+
+        let errors = parse_input::<f32>("1.5\n2.5e", true).unwrap_err().0;
+        assert_eq!((2, 4), (errors[0].line, errors[0].column));
+
+        let input = "10 199\n  20\t200\n30 2x8\n40\n5y 210";
+        let errors = parse_input_with(input, true, parse_record).unwrap_err().0;
+        let locations = errors
+            .iter()
+            .map(|err| (err.line, err.column, err.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![(3, 5, "30 2x8"), (4, 3, "40"), (5, 2, "5y 210")],
+            locations
+        );
+        assert_eq!(
+            Ok(vec![(10, 199), (20, 200)]),
+            parse_input_with("10 199\n  20\t200", false, parse_record)
+        );
+    }
+
+    #[test]
+    fn window_too_large() {
+        let input = [199, 200, 208];
+
+        assert_eq!(
+            Err(WindowError { window: 3, len: 3 }),
+            count_increases::<3>(&input)
+        );
+        assert_eq!(
+            Err(WindowError { window: 4, len: 3 }),
+            count_increases_by(&input, 4)
+        );
     }
 }
@@ -0,0 +1,83 @@
+use std::{num::NonZeroUsize, thread};
+
+use super::{check_window, WindowError};
+
+/// The number of comparisons done at the same time by `count_pairs`.
+const LANES: usize = 8;
+
+/// The number of chunks accumulated in `u32` lanes before adding them to the total. This keeps
+/// the lanes from overflowing.
+const BLOCK: usize = 1 << 20;
+
+/// Count the number of positions where `after` is larger than `before`.
+///
+/// The comparisons are done in chunks of `LANES` elements with independent accumulators so the
+/// compiler can turn the inner loop into vector instructions.
+fn count_pairs(before: &[u32], after: &[u32]) -> usize {
+    debug_assert_eq!(before.len(), after.len());
+
+    let before_chunks = before.chunks_exact(LANES);
+    let after_chunks = after.chunks_exact(LANES);
+
+    // The elements that do not fill a whole chunk are compared one by one.
+    let mut count = before_chunks
+        .remainder()
+        .iter()
+        .zip(after_chunks.remainder())
+        .filter(|(x, y)| y > x)
+        .count();
+
+    let mut lanes = [0u32; LANES];
+    for (i, (xs, ys)) in before_chunks.zip(after_chunks).enumerate() {
+        for ((lane, x), y) in lanes.iter_mut().zip(xs).zip(ys) {
+            *lane += (y > x) as u32;
+        }
+
+        if (i + 1) % BLOCK == 0 {
+            count += lanes.iter().map(|&lane| lane as usize).sum::<usize>();
+            lanes = [0; LANES];
+        }
+    }
+
+    count + lanes.iter().map(|&lane| lane as usize).sum::<usize>()
+}
+
+/// Same as `count_increases_by` but the comparisons are done in vectorizable chunks.
+#[inline(never)]
+pub fn count_increases_chunked(slice: &[u32], window: usize) -> Result<usize, WindowError> {
+    check_window(window, slice.len())?;
+
+    let len = slice.len() - window;
+
+    Ok(count_pairs(&slice[..len], &slice[window..]))
+}
+
+/// Same as `count_increases_chunked` but the input is split between `threads` threads.
+#[inline(never)]
+pub fn count_increases_parallel(
+    slice: &[u32],
+    window: usize,
+    threads: NonZeroUsize,
+) -> Result<usize, WindowError> {
+    check_window(window, slice.len())?;
+
+    let len = slice.len() - window;
+    let (before, after) = (&slice[..len], &slice[window..]);
+    // Round up so there are at most `threads` chunks.
+    let chunk_len = len.div_ceil(threads.get()).max(1);
+
+    let count = thread::scope(|scope| {
+        let handles = before
+            .chunks(chunk_len)
+            .zip(after.chunks(chunk_len))
+            .map(|(before, after)| scope.spawn(move || count_pairs(before, after)))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum()
+    });
+
+    Ok(count)
+}
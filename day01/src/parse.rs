@@ -0,0 +1,144 @@
+use std::{fmt, io::BufRead, str::FromStr};
+
+use anyhow::{Context, Error};
+
+/// Error returned when a line of the input cannot be parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineError {
+    /// The 1-based number of the line.
+    pub line: usize,
+    /// The 1-based column of the first character that could not be parsed.
+    pub column: usize,
+    /// The contents of the line.
+    pub text: String,
+    /// The message of the underlying parsing error.
+    pub message: String,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: could not parse {:?}: {}",
+            self.line, self.column, self.text, self.message
+        )
+    }
+}
+
+impl std::error::Error for LineError {}
+
+/// All the lines that could not be parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseErrors(pub Vec<LineError>);
+
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [err] = self.0.as_slice() {
+            return err.fmt(f);
+        }
+
+        write!(f, "could not parse {} input lines", self.0.len())?;
+        for err in &self.0 {
+            write!(f, "\n  {}", err)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseErrors {}
+
+/// Parse the line at the 0-based `index`.
+pub fn parse_line<T>(index: usize, text: &str) -> Result<T, LineError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    text.parse().map_err(|err: T::Err| {
+        // The column where parsing failed is the one after the longest prefix that can be
+        // parsed.
+        let column = text
+            .char_indices()
+            .skip(1)
+            .map(|(end, _)| end)
+            .chain(Some(text.len()))
+            .take_while(|&end| text[..end].parse::<T>().is_ok())
+            .last()
+            .map_or(1, |end| text[..end].chars().count() + 1);
+
+        LineError {
+            line: index + 1,
+            column,
+            text: text.to_owned(),
+            message: err.to_string(),
+        }
+    })
+}
+
+/// Parse the line at the 0-based `index` as a timestamp and a depth separated by whitespace.
+pub fn parse_record(index: usize, text: &str) -> Result<(u64, u32), LineError> {
+    let rest = text.trim_start();
+    let (timestamp, depth) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let depth = depth.trim_start();
+
+    // The fields are parsed on their own, so their columns are moved to where they start.
+    let moved = |field: &str| {
+        let start = text[..text.len() - field.len()].chars().count();
+        move |err: LineError| LineError {
+            column: start + err.column,
+            text: text.to_owned(),
+            ..err
+        }
+    };
+
+    Ok((
+        parse_line(index, timestamp).map_err(moved(rest))?,
+        parse_line(index, depth).map_err(moved(depth))?,
+    ))
+}
+
+/// Parse each line of `text`. If `all` is `true`, every line that cannot be parsed is reported,
+/// otherwise parsing stops at the first error.
+pub fn parse_input<T>(text: &str, all: bool) -> Result<Vec<T>, ParseErrors>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_input_with(text, all, parse_line)
+}
+
+/// Parse each line of `text` with `parse`, reporting errors like `parse_input`.
+pub fn parse_input_with<T>(
+    text: &str,
+    all: bool,
+    parse: impl Fn(usize, &str) -> Result<T, LineError>,
+) -> Result<Vec<T>, ParseErrors> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        match parse(index, line) {
+            Ok(value) => values.push(value),
+            Err(err) => {
+                errors.push(err);
+                if !all {
+                    break;
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(ParseErrors(errors))
+    }
+}
+
+/// Parse each line of `reader` as an integer without reading the whole input first.
+pub fn parse_lines(reader: impl BufRead) -> impl Iterator<Item = Result<u32, Error>> {
+    reader.lines().enumerate().map(|(index, line)| {
+        let line = line.context("could not read input line")?;
+        Ok(parse_line(index, &line)?)
+    })
+}
@@ -0,0 +1,153 @@
+use std::{collections::BTreeMap, fmt};
+
+/// A run of consecutive values, both ends inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Run {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Run {
+    /// The number of values in the run.
+    pub fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+/// The change between two consecutive values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    /// The index of the value before the jump.
+    pub index: usize,
+    pub delta: i64,
+}
+
+/// Statistics about the differences between consecutive depths.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DepthReport {
+    pub increases: usize,
+    pub decreases: usize,
+    pub plateaus: usize,
+    /// The longest run of strictly increasing values. If several runs have the same length, the
+    /// first one is kept.
+    pub longest_increasing_run: Option<Run>,
+    /// The jump with the largest absolute delta. If several jumps have the same magnitude, the
+    /// first one is kept.
+    pub largest_jump: Option<Jump>,
+    /// The number of times each delta appears.
+    pub delta_histogram: BTreeMap<i64, usize>,
+}
+
+impl DepthReport {
+    pub fn new(depths: &[u32]) -> Self {
+        let mut report = Self::default();
+
+        if depths.is_empty() {
+            return report;
+        }
+
+        let mut longest = Run { start: 0, end: 0 };
+        let mut current = longest;
+
+        for (index, pair) in depths.windows(2).enumerate() {
+            let delta = i64::from(pair[1]) - i64::from(pair[0]);
+
+            if delta > 0 {
+                report.increases += 1;
+                current.end = index + 1;
+                if current.len() > longest.len() {
+                    longest = current;
+                }
+            } else {
+                if delta < 0 {
+                    report.decreases += 1;
+                } else {
+                    report.plateaus += 1;
+                }
+                // The run is broken so a new one starts at the next value.
+                current = Run {
+                    start: index + 1,
+                    end: index + 1,
+                };
+            }
+
+            if report
+                .largest_jump
+                .is_none_or(|jump| delta.abs() > jump.delta.abs())
+            {
+                report.largest_jump = Some(Jump { index, delta });
+            }
+
+            *report.delta_histogram.entry(delta).or_insert(0) += 1;
+        }
+
+        report.longest_increasing_run = Some(longest);
+
+        report
+    }
+
+    /// Render the report as a JSON object.
+    pub fn to_json(&self) -> String {
+        let run = match self.longest_increasing_run {
+            Some(run) => format!(
+                "{{\"start\":{},\"end\":{},\"len\":{}}}",
+                run.start,
+                run.end,
+                run.len()
+            ),
+            None => "null".to_owned(),
+        };
+
+        let jump = match self.largest_jump {
+            Some(jump) => format!("{{\"index\":{},\"delta\":{}}}", jump.index, jump.delta),
+            None => "null".to_owned(),
+        };
+
+        // JSON keys must be strings so the deltas are quoted.
+        let histogram = self
+            .delta_histogram
+            .iter()
+            .map(|(delta, count)| format!("\"{}\":{}", delta, count))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"increases\":{},\"decreases\":{},\"plateaus\":{},\"longest_increasing_run\":{},\"largest_jump\":{},\"delta_histogram\":{{{}}}}}",
+            self.increases, self.decreases, self.plateaus, run, jump, histogram
+        )
+    }
+}
+
+impl fmt::Display for DepthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{:>12}", "increases", self.increases)?;
+        writeln!(f, "{:<24}{:>12}", "decreases", self.decreases)?;
+        writeln!(f, "{:<24}{:>12}", "plateaus", self.plateaus)?;
+
+        if let Some(run) = self.longest_increasing_run {
+            let range = format!("{}..={}", run.start, run.end);
+            writeln!(
+                f,
+                "{:<24}{:>12} ({} values)",
+                "longest increasing run",
+                range,
+                run.len()
+            )?;
+        }
+
+        if let Some(jump) = self.largest_jump {
+            writeln!(
+                f,
+                "{:<24}{:>+12} (at {})",
+                "largest jump", jump.delta, jump.index
+            )?;
+        }
+
+        writeln!(f, "{:<24}{:>12}", "delta", "count")?;
+        for (delta, count) in &self.delta_histogram {
+            writeln!(f, "{:<+24}{:>12}", delta, count)?;
+        }
+
+        Ok(())
+    }
+}
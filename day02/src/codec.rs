@@ -0,0 +1,135 @@
+use std::fmt;
+
+use super::Command;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a command.
+    UnexpectedEnd,
+    /// The byte at `offset` is not the kind of any command.
+    UnknownKind { offset: usize, kind: u8 },
+    /// The integer starting at `offset` does not fit in a `usize`.
+    Overflow { offset: usize },
+    /// There are bytes left after the last command.
+    TrailingBytes { offset: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::UnknownKind { offset, kind } => {
+                write!(f, "unknown command kind {} at offset {}", kind, offset)
+            }
+            Self::Overflow { offset } => write!(f, "integer at offset {} is too large", offset),
+            Self::TrailingBytes { offset } => write!(f, "trailing bytes at offset {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn write_integer(bytes: &mut Vec<u8>, mut value: usize) {
+    // Write seven bits at a time, setting the highest bit of every byte except the last one.
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Encode `commands` into bytes.
+///
+/// The encoding starts with the number of commands and then has one byte with the kind of each
+/// command followed by its value. Both the length and the values are encoded as unsigned LEB128
+/// integers so small values take a single byte.
+pub fn encode(commands: &[Command]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(2 * commands.len() + 1);
+    write_integer(&mut bytes, commands.len());
+
+    for command in commands {
+        let (kind, value) = match *command {
+            Command::Forward(value) => (0, value),
+            Command::Back(value) => (1, value),
+            Command::Down(value) => (2, value),
+            Command::Up(value) => (3, value),
+            Command::Left(value) => (4, value),
+            Command::Right(value) => (5, value),
+            Command::Pitch(value) => (6, value),
+        };
+        bytes.push(kind);
+        write_integer(&mut bytes, value);
+    }
+
+    bytes
+}
+
+/// Reads the values of an encoded list of commands.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self
+            .bytes
+            .get(self.offset)
+            .ok_or(DecodeError::UnexpectedEnd)?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn integer(&mut self) -> Result<usize, DecodeError> {
+        let offset = self.offset;
+        let mut value = 0usize;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.byte()?;
+            let bits = usize::from(byte & 0x7f);
+
+            // Reject the bits that would be shifted out of the integer.
+            if shift >= usize::BITS || (bits << shift) >> shift != bits {
+                return Err(DecodeError::Overflow { offset });
+            }
+            value |= bits << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+/// Decode a list of commands encoded with `encode`.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Command>, DecodeError> {
+    let mut reader = Reader { bytes, offset: 0 };
+    let len = reader.integer()?;
+    // Every command takes at least two bytes, so don't trust larger lengths when allocating.
+    let mut commands = Vec::with_capacity(len.min(bytes.len() / 2));
+
+    for _ in 0..len {
+        let offset = reader.offset;
+        let command = match reader.byte()? {
+            0 => Command::Forward,
+            1 => Command::Back,
+            2 => Command::Down,
+            3 => Command::Up,
+            4 => Command::Left,
+            5 => Command::Right,
+            6 => Command::Pitch,
+            kind => return Err(DecodeError::UnknownKind { offset, kind }),
+        };
+        commands.push(command(reader.integer()?));
+    }
+
+    if reader.offset != bytes.len() {
+        return Err(DecodeError::TrailingBytes {
+            offset: reader.offset,
+        });
+    }
+
+    Ok(commands)
+}
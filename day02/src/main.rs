@@ -1,11 +1,36 @@
-use anyhow::{Context, Error};
-use std::{fs::read_to_string, num::ParseIntError, str::FromStr};
+use anyhow::{bail, Context, Error};
+use navigator::{Drift, Models, NavigationError, Navigator, Plain, SurfacePolicy, WithAim};
+use planner::{plan_plain, plan_with_aim};
+use script::parse_script;
+use spatial::Spatial;
+use std::{
+    fs::{read, read_to_string},
+    io::{stdin, stdout, Write},
+    num::NonZeroUsize,
+    num::ParseIntError,
+    str::FromStr,
+};
 
-#[derive(Debug)]
+mod codec;
+mod navigator;
+mod planner;
+mod replay;
+mod script;
+mod spatial;
+mod trajectory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Command {
     Forward(usize),
+    Back(usize),
     Down(usize),
     Up(usize),
+    /// Turn left the given number of degrees.
+    Left(usize),
+    /// Turn right the given number of degrees.
+    Right(usize),
+    /// Pitch the nose down the given number of degrees.
+    Pitch(usize),
 }
 
 #[derive(Debug)]
@@ -13,6 +38,18 @@ enum CommandParseError {
     MissingSpace,
     UnknownCommand,
     Int(ParseIntError),
+    /// The script ended in the middle of a command or block.
+    UnexpectedEnd,
+    /// A block was expected to start.
+    ExpectedBrace,
+    /// A block started without a `repeat` or `macro`.
+    UnexpectedBrace,
+    /// A block was closed without being opened.
+    UnmatchedBrace,
+    /// A macro was defined with the name of a keyword.
+    InvalidMacroName,
+    /// A block expands to more commands than a script can have.
+    TooManyCommands,
 }
 
 impl From<ParseIntError> for CommandParseError {
@@ -27,25 +64,67 @@ impl std::fmt::Display for CommandParseError {
             Self::MissingSpace => write!(f, "missing space separator"),
             Self::UnknownCommand => write!(f, "unknown command prefix"),
             Self::Int(err) => err.fmt(f),
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::ExpectedBrace => write!(f, "expected `{{`"),
+            Self::UnexpectedBrace => write!(f, "unexpected `{{`"),
+            Self::UnmatchedBrace => write!(f, "unmatched `}}`"),
+            Self::InvalidMacroName => write!(f, "invalid macro name"),
+            Self::TooManyCommands => write!(f, "the script expands to too many commands"),
         }
     }
 }
 
 impl std::error::Error for CommandParseError {}
 
+impl Command {
+    /// The word used for this command in the input.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Forward(_) => "forward",
+            Self::Back(_) => "back",
+            Self::Down(_) => "down",
+            Self::Up(_) => "up",
+            Self::Left(_) => "left",
+            Self::Right(_) => "right",
+            Self::Pitch(_) => "pitch",
+        }
+    }
+
+    fn value(&self) -> usize {
+        match self {
+            Self::Forward(value)
+            | Self::Back(value)
+            | Self::Down(value)
+            | Self::Up(value)
+            | Self::Left(value)
+            | Self::Right(value)
+            | Self::Pitch(value) => *value,
+        }
+    }
+}
+
+/// Commands are displayed with the same syntax used to parse them.
+impl std::fmt::Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name(), self.value())
+    }
+}
+
 impl FromStr for Command {
     type Err = CommandParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (head, tail) = s
-            .split_once(' ')
-            .ok_or_else(|| CommandParseError::MissingSpace)?;
+        let (head, tail) = s.split_once(' ').ok_or(CommandParseError::MissingSpace)?;
         let value = usize::from_str(tail)?;
 
         let command = match head {
             "forward" => Self::Forward(value),
+            "back" => Self::Back(value),
             "down" => Self::Down(value),
             "up" => Self::Up(value),
+            "left" => Self::Left(value),
+            "right" => Self::Right(value),
+            "pitch" => Self::Pitch(value),
             _ => return Err(CommandParseError::UnknownCommand),
         };
 
@@ -53,48 +132,189 @@ impl FromStr for Command {
     }
 }
 
-fn final_position(commands: &[Command]) -> (usize, usize) {
-    let (mut x, mut y) = (0, 0);
+/// Merge consecutive commands that move in the same direction and remove the ones with value
+/// zero.
+///
+/// The final state is the same in every model and for every surface policy, but the indices
+/// reported by navigation errors refer to the normalized commands.
+fn normalize(commands: &[Command]) -> Vec<Command> {
+    let mut normalized: Vec<Command> = Vec::with_capacity(commands.len());
 
-    for command in commands {
-        match command {
-            Command::Forward(dx) => x += dx,
-            Command::Down(dy) => y += dy,
-            Command::Up(dy) => y -= dy,
+    for &command in commands {
+        if command.value() == 0 {
+            continue;
+        }
+
+        let merged = match (normalized.last_mut(), command) {
+            (Some(Command::Forward(last)), Command::Forward(value))
+            | (Some(Command::Back(last)), Command::Back(value))
+            | (Some(Command::Down(last)), Command::Down(value))
+            | (Some(Command::Up(last)), Command::Up(value))
+            | (Some(Command::Left(last)), Command::Left(value))
+            | (Some(Command::Right(last)), Command::Right(value))
+            | (Some(Command::Pitch(last)), Command::Pitch(value)) => {
+                // Keep both commands if their sum does not fit in a single command.
+                last.checked_add(value).map(|sum| *last = sum).is_some()
+            }
+            _ => false,
+        };
+
+        if !merged {
+            normalized.push(command);
         }
     }
 
-    (x, y)
+    normalized
+}
+
+/// Compute the final position where each command moves the submarine directly.
+fn final_position(
+    commands: &[Command],
+    policy: SurfacePolicy,
+) -> Result<(i64, i64), NavigationError> {
+    Plain.run(commands, policy)
+}
+
+/// Compute the final position and aim where the up and down commands change the aim of the
+/// submarine.
+fn final_position_with_aim(
+    commands: &[Command],
+    policy: SurfacePolicy,
+) -> Result<(i64, i64, i64), NavigationError> {
+    WithAim.run(commands, policy)
 }
 
-fn final_position_with_aim(commands: &[Command]) -> (usize, usize, usize) {
-    let (mut x, mut y, mut aim) = (0, 0, 0);
+fn main() -> Result<(), Error> {
+    let mut models = Models::new();
+    // Custom models are registered next to the built-in ones.
+    models.register("drift", Drift { rate: 1 });
+    models.register("3d", Spatial { aim: false });
+    models.register("3d-aim", Spatial { aim: true });
+
+    let mut policy = SurfacePolicy::Clamp;
+    let mut model = None;
+    let mut output = None;
+    let mut target = None;
+    let mut max = NonZeroUsize::MAX;
+    let mut binary = false;
+    let mut normalized = false;
 
-    for command in commands {
-        match command {
-            Command::Forward(dx) => {
-                x += dx;
-                y += aim * dx;
+    // The surface policy can be passed as an argument. `--model=<name>` selects the
+    // interpretation of the commands and `--csv`, `--svg` or `--summary` print its trajectory
+    // instead of the answer. `--plan=<x>,<depth>[,<aim>]` prints the commands that reach that
+    // target instead, using commands no larger than `--max=<n>`. `--binary` reads the input in
+    // the binary encoding, `--normalize` merges consecutive commands in the same direction and
+    // `--text` or `--encode` print the commands as text or in the binary encoding. `--trace`
+    // prints the state after each command and `--debug` starts an interactive debugger.
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "clamp" => policy = SurfacePolicy::Clamp,
+            "negative" => policy = SurfacePolicy::AllowNegative,
+            "error" => policy = SurfacePolicy::Error,
+            "--csv" | "--svg" | "--summary" | "--text" | "--encode" | "--trace" | "--debug" => {
+                output = Some(arg)
+            }
+            "--binary" => binary = true,
+            "--normalize" => normalized = true,
+            _ if arg.starts_with("--model=") => model = Some(arg["--model=".len()..].to_owned()),
+            _ if arg.starts_with("--plan=") => {
+                let values = arg["--plan=".len()..]
+                    .split(',')
+                    .map(|value| value.parse())
+                    .collect::<Result<Vec<i64>, _>>()
+                    .context("could not parse target")?;
+                match values[..] {
+                    [x, depth] => target = Some(((x, depth), None)),
+                    [x, depth, aim] => target = Some(((x, depth), Some(aim))),
+                    _ => bail!("expected a target like `<x>,<depth>[,<aim>]`"),
+                }
             }
-            Command::Down(daim) => aim += daim,
-            Command::Up(daim) => aim -= daim,
+            _ if arg.starts_with("--max=") => {
+                max = arg["--max=".len()..]
+                    .parse()
+                    .context("could not parse maximum magnitude")?;
+            }
+            _ => bail!("unknown argument {:?}", arg),
         }
     }
 
-    (x, y, aim)
-}
+    if let Some((position, aim)) = target {
+        let commands = match model.as_deref().unwrap_or("aim") {
+            "plain" => plan_plain(position, aim, max)?,
+            "aim" => plan_with_aim(position, aim, max)?,
+            name => bail!("cannot plan with model {:?}", name),
+        };
 
-fn main() -> Result<(), Error> {
-    let input = read_to_string("./input")
-        .context("could not read input file")?
-        .lines()
-        .map(|line| line.parse().context("could not parse input line"))
-        .collect::<Result<Vec<Command>, _>>()?;
+        for command in commands {
+            println!("{}", command);
+        }
 
-    let (x, y) = final_position(&input);
+        return Ok(());
+    }
+
+    let mut input = if binary {
+        codec::decode(&read("./input").context("could not read input file")?)
+            .context("could not decode input")?
+    } else {
+        parse_script(&read_to_string("./input").context("could not read input file")?)
+            .context("could not parse input")?
+    };
+
+    if normalized {
+        input = normalize(&input);
+    }
+
+    match output.as_deref() {
+        Some("--text") => {
+            for command in &input {
+                println!("{}", command);
+            }
+            return Ok(());
+        }
+        Some("--encode") => {
+            stdout()
+                .write_all(&codec::encode(&input))
+                .context("could not write output")?;
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if model.is_some() || output.is_some() {
+        let name = model.as_deref().unwrap_or("aim");
+        let model = models.get(name).with_context(|| {
+            let names = models.names().collect::<Vec<_>>().join(", ");
+            format!("unknown model {:?}, expected one of {}", name, names)
+        })?;
+
+        match output.as_deref() {
+            None => {
+                let snapshot = model.final_snapshot(&input, policy)?;
+                println!("{}: {}", name, snapshot.x * snapshot.y);
+            }
+            Some("--csv") => print!("{}", model.trajectory(&input, policy)?.to_csv(&input)),
+            Some("--svg") => print!("{}", model.trajectory(&input, policy)?.to_svg()),
+            Some("--trace") => {
+                replay::trace(&mut *model.replay(&input, policy), &mut stdout().lock())?
+            }
+            Some("--debug") => replay::debug(
+                &mut *model.replay(&input, policy),
+                &mut stdin().lock(),
+                &mut stdout().lock(),
+            )?,
+            Some(_) => match model.trajectory(&input, policy)?.summary() {
+                Some(summary) => print!("{}", summary),
+                None => eprintln!("Input is empty"),
+            },
+        }
+
+        return Ok(());
+    }
+
+    let (x, y) = final_position(&input, policy)?;
     println!("Part 1: {}", x * y);
 
-    let (x, y, _aim) = final_position_with_aim(&input);
+    let (x, y, _aim) = final_position_with_aim(&input, policy)?;
     println!("Part 2: {}", x * y);
 
     Ok(())
@@ -118,7 +338,7 @@ mod tests {
         .map(|s| s.parse().unwrap())
         .collect::<Vec<Command>>();
 
-        assert_eq!((15, 10), final_position(&input));
+        assert_eq!(Ok((15, 10)), final_position(&input, SurfacePolicy::Error));
     }
 
     #[test]
@@ -135,7 +355,453 @@ mod tests {
         .map(|s| s.parse().unwrap())
         .collect::<Vec<Command>>();
 
-        let (x, y, _aim) = final_position_with_aim(&input);
+        let (x, y, _aim) = final_position_with_aim(&input, SurfacePolicy::Error).unwrap();
         assert_eq!((15, 60), (x, y));
     }
+
+    #[test]
+    fn trajectory() {
+        let input = [
+            "forward 5",
+            "down 5",
+            "forward 8",
+            "up 3",
+            "down 8",
+            "forward 2",
+        ]
+        .into_iter()
+        .map(|s| s.parse().unwrap())
+        .collect::<Vec<Command>>();
+
+        let trajectory = Models::new()
+            .get("aim")
+            .unwrap()
+            .trajectory(&input, SurfacePolicy::Error)
+            .unwrap();
+        let depths = trajectory
+            .snapshots
+            .iter()
+            .map(|snapshot| snapshot.y)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0, 0, 40, 40, 40, 60], depths);
+
+        let summary = trajectory.summary().unwrap();
+        assert_eq!((60, 5), summary.max_depth);
+        assert_eq!((0, 0), summary.min_depth);
+        assert_eq!(
+            navigator::Snapshot {
+                x: 15,
+                y: 60,
+                aim: 10,
+                z: 0,
+            },
+            summary.last
+        );
+
+        let csv = trajectory.to_csv(&input);
+        assert_eq!(Some("2,forward,8,13,40,5,0"), csv.lines().nth(3));
+        assert!(trajectory
+            .to_svg()
+            .contains("points=\"0,0 5,0 5,0 13,40 13,40 13,40 15,60\""));
+    }
+
+    #[test]
+    fn custom_model() {
+        let input = ["forward 5", "up 3", "forward 2"]
+            .into_iter()
+            .map(|s| s.parse().unwrap())
+            .collect::<Vec<Command>>();
+
+        let mut models = Models::new();
+        models.register("drift", Drift { rate: 2 });
+        let model = models.get("drift").unwrap();
+
+        assert_eq!(
+            (7, 11),
+            Drift { rate: 2 }.run(&input, SurfacePolicy::Error).unwrap()
+        );
+        assert_eq!(
+            11,
+            model
+                .final_snapshot(&input, SurfacePolicy::Error)
+                .unwrap()
+                .y
+        );
+        assert_eq!(
+            vec!["aim", "drift", "plain"],
+            models.names().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn script() {
+        let input = "\
+# Dive twice.
+macro dive {
+    down 5
+    forward 2
+}
+
+repeat 2 { dive } # inline block
+back 1
+up 3
+";
+        assert_eq!(
+            vec![
+                Command::Down(5),
+                Command::Forward(2),
+                Command::Down(5),
+                Command::Forward(2),
+                Command::Back(1),
+                Command::Up(3),
+            ],
+            parse_script(input).unwrap()
+        );
+
+        let lines = ["forward 5", "down 5", "up 3"];
+        assert_eq!(
+            parse_script(&lines.join("\n")).unwrap(),
+            lines
+                .into_iter()
+                .map(|s| s.parse().unwrap())
+                .collect::<Vec<Command>>()
+        );
+
+        let error = |input| {
+            let err = parse_script(input).unwrap_err();
+            (err.line, err.column, err.error.to_string())
+        };
+        assert_eq!(
+            (2, 9, "invalid digit found in string".to_owned()),
+            error("down 1\nforward x")
+        );
+        assert_eq!(
+            (1, 1, "unknown command prefix".to_owned()),
+            error("dive\nmacro dive { down 1 }")
+        );
+        assert_eq!((2, 1, "unmatched `}`".to_owned()), error("down 1\n}"));
+        assert_eq!(
+            (2, 7, "unexpected end of input".to_owned()),
+            error("repeat 2 {\ndown 1")
+        );
+
+        // Scripts that expand to too many commands are rejected before expanding them.
+        let too_many = (2, 3, "the script expands to too many commands".to_owned());
+        assert_eq!(
+            too_many,
+            error("down 1\n  repeat 100000000000000000 { forward 1 }")
+        );
+        assert_eq!(
+            too_many,
+            error("down 1\n  repeat 2000 { repeat 2000 { forward 1 } }")
+        );
+        assert_eq!(
+            (2, 5, too_many.2),
+            error("macro m { repeat 1000000 { up 1 } }\n  m m")
+        );
+    }
+
+    #[test]
+    fn planner() {
+        let max = NonZeroUsize::new(4).unwrap();
+
+        assert_eq!(
+            Ok(vec![
+                Command::Forward(4),
+                Command::Forward(1),
+                Command::Up(3)
+            ]),
+            plan_plain((5, -3), None, max)
+        );
+        assert_eq!(
+            Err(planner::PlanError::AimUnreachable),
+            plan_plain((5, 3), Some(1), max)
+        );
+
+        // Breadth-first search of the fewest commands no larger than 3 reaching each state in a
+        // box around the origin. The box is large enough for the targets checked below.
+        const BOUND: i64 = 8;
+        let index = |(x, depth, aim): (i64, i64, i64)| {
+            let side = 2 * BOUND + 1;
+            let depths = 8 * BOUND * BOUND + 1;
+            let in_box = x.abs() <= BOUND && aim.abs() <= BOUND && depth.abs() <= 4 * BOUND * BOUND;
+            in_box.then(|| {
+                (((x + BOUND) * side + aim + BOUND) * depths + depth + 4 * BOUND * BOUND) as usize
+            })
+        };
+
+        let mut fewest = vec![usize::MAX; index((BOUND, 4 * BOUND * BOUND, BOUND)).unwrap() + 1];
+        let mut queue = std::collections::VecDeque::from([(0, 0, 0)]);
+        fewest[index((0, 0, 0)).unwrap()] = 0;
+        while let Some((x, depth, aim)) = queue.pop_front() {
+            let len = fewest[index((x, depth, aim)).unwrap()];
+            for n in 1..=3 {
+                for next in [
+                    (x + n, depth + aim * n, aim),
+                    (x - n, depth - aim * n, aim),
+                    (x, depth, aim + n),
+                    (x, depth, aim - n),
+                ] {
+                    if let Some(i) = index(next).filter(|&i| fewest[i] == usize::MAX) {
+                        fewest[i] = len + 1;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        let max = NonZeroUsize::new(3).unwrap();
+        for x in -3..=4 {
+            for depth in -8..=16 {
+                for aim in [None, Some(-2), Some(3)] {
+                    let commands = plan_with_aim((x, depth), aim, max).unwrap();
+                    assert!(commands.iter().all(|command| command.value() <= 3));
+
+                    let (end_x, end_depth, end_aim) = WithAim
+                        .run(&commands, SurfacePolicy::AllowNegative)
+                        .unwrap();
+                    assert_eq!((x, depth), (end_x, end_depth));
+                    assert!(aim.is_none_or(|aim| aim == end_aim));
+
+                    let optimum = match aim {
+                        Some(aim) => fewest[index((x, depth, aim)).unwrap()],
+                        None => (-BOUND..=BOUND)
+                            .map(|aim| fewest[index((x, depth, aim)).unwrap()])
+                            .min()
+                            .unwrap(),
+                    };
+                    assert_eq!(optimum, commands.len(), "({x}, {depth}, {aim:?})");
+                }
+            }
+        }
+
+        // Back up so a single cruise covers the depth.
+        assert_eq!(
+            Ok(vec![
+                Command::Back(2),
+                Command::Down(3),
+                Command::Forward(3)
+            ]),
+            plan_with_aim((1, 9), None, max)
+        );
+
+        let max = NonZeroUsize::new(4).unwrap();
+        // Cruise 3 units at aim 4.
+        assert_eq!(
+            Ok(vec![Command::Down(4), Command::Forward(3)]),
+            plan_with_aim((3, 12), None, max)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = [
+            Command::Forward(5),
+            Command::Back(0),
+            Command::Down(127),
+            Command::Up(128),
+            Command::Forward(usize::MAX),
+        ];
+
+        for command in input {
+            assert_eq!(command, command.to_string().parse().unwrap());
+        }
+
+        let bytes = codec::encode(&input);
+        assert_eq!(&[5, 0, 5, 1, 0, 2, 127, 3, 128, 1], &bytes[..10]);
+        assert_eq!(Ok(input.to_vec()), codec::decode(&bytes));
+        assert_eq!(
+            Err(codec::DecodeError::UnexpectedEnd),
+            codec::decode(&bytes[..bytes.len() - 1])
+        );
+        assert_eq!(
+            Err(codec::DecodeError::UnknownKind { offset: 1, kind: 7 }),
+            codec::decode(&[1, 7, 0])
+        );
+        assert_eq!(
+            Err(codec::DecodeError::TrailingBytes { offset: 3 }),
+            codec::decode(&[1, 0, 0, 0])
+        );
+        assert_eq!(
+            Err(codec::DecodeError::Overflow { offset: 2 }),
+            codec::decode(&[1, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f])
+        );
+    }
+
+    #[test]
+    fn normalize_commands() {
+        let input = parse_script(
+            "forward 5\nforward 0\nforward 3\ndown 2\ndown 2\nup 9\nup 1\nforward 2\nback 1\nback 1",
+        )
+        .unwrap();
+        let normalized = normalize(&input);
+
+        assert_eq!(
+            vec![
+                Command::Forward(8),
+                Command::Down(4),
+                Command::Up(10),
+                Command::Forward(2),
+                Command::Back(2),
+            ],
+            normalized
+        );
+        for policy in [SurfacePolicy::Clamp, SurfacePolicy::AllowNegative] {
+            assert_eq!(
+                final_position(&input, policy),
+                final_position(&normalized, policy)
+            );
+            assert_eq!(
+                final_position_with_aim(&input, policy),
+                final_position_with_aim(&normalized, policy)
+            );
+        }
+
+        let input = [Command::Up(usize::MAX), Command::Up(1)];
+        assert_eq!(input.to_vec(), normalize(&input));
+    }
+
+    #[test]
+    fn spatial() {
+        use navigator::Model;
+
+        let input = parse_script("forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2").unwrap();
+
+        let state = Spatial { aim: false }
+            .run(&input, SurfacePolicy::Error)
+            .unwrap();
+        assert_eq!((15.0, 10.0), (state.x, state.depth));
+        let state = Spatial { aim: true }
+            .run(&input, SurfacePolicy::Error)
+            .unwrap();
+        assert_eq!((15.0, 60.0, 10), (state.x, state.depth, state.aim));
+
+        let input = parse_script("right 90\nforward 10\nleft 450\npitch 90\nforward 4").unwrap();
+        let snapshot = Spatial { aim: false }
+            .final_snapshot(&input, SurfacePolicy::Error)
+            .unwrap();
+        assert_eq!((0, 4, 10), (snapshot.x, snapshot.y, snapshot.z));
+
+        // The planar models cannot turn.
+        assert_eq!(
+            Err(NavigationError::Unsupported { index: 0 }),
+            final_position(&input, SurfacePolicy::Error)
+        );
+    }
+
+    #[test]
+    fn replay() {
+        use replay::{debug, resume, trace, Breakpoint, Replay, Stepper};
+
+        let input = parse_script("forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2").unwrap();
+        let mut replay = Replay::new(&WithAim, &input, SurfacePolicy::Error);
+
+        assert!(replay.forward().unwrap());
+        assert!(replay.forward().unwrap());
+        assert!(replay.forward().unwrap());
+        assert_eq!((13, 40), (replay.snapshot().x, replay.snapshot().y));
+        assert!(replay.back());
+        assert_eq!(2, replay.position());
+        assert_eq!((5, 0), (replay.snapshot().x, replay.snapshot().y));
+
+        let breakpoints = [Breakpoint::Index(5), Breakpoint::Depth(20)];
+        assert_eq!(
+            Ok(Some(Breakpoint::Depth(20))),
+            resume(&mut replay, &breakpoints, false)
+        );
+        assert_eq!(3, replay.position());
+        assert_eq!(
+            Ok(Some(Breakpoint::Index(5))),
+            resume(&mut replay, &breakpoints, false)
+        );
+        assert_eq!(Ok(None), resume(&mut replay, &breakpoints, false));
+        assert_eq!(6, replay.position());
+        assert_eq!(
+            Ok(Some(Breakpoint::Index(5))),
+            resume(&mut replay, &breakpoints, true)
+        );
+
+        let mut output = Vec::new();
+        trace(
+            &mut Replay::new(&Plain, &input, SurfacePolicy::Error),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(7, output.lines().count());
+        assert_eq!(
+            Some("[6/6] x = 15, depth = 10, aim = 0, z = 0, done"),
+            output.lines().last()
+        );
+
+        let mut output = Vec::new();
+        debug(
+            &mut Replay::new(&WithAim, &input, SurfacePolicy::Error),
+            &mut "break 4\ncontinue\nback 2\nquit\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            "\
+[0/6] x = 0, depth = 0, aim = 0, z = 0, next: forward 5
+> > hit Index(4)
+[4/6] x = 13, depth = 40, aim = 2, z = 0, next: down 8
+> [2/6] x = 5, depth = 0, aim = 5, z = 0, next: forward 8
+> ",
+            output
+        );
+
+        let mut output = Vec::new();
+        debug(
+            &mut Replay::new(&WithAim, &input, SurfacePolicy::Error),
+            &mut "step 100000000000000
+back 100000000000000
+"
+            .as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            "\
+[0/6] x = 0, depth = 0, aim = 0, z = 0, next: forward 5
+> [6/6] x = 15, depth = 60, aim = 10, z = 0, done
+> [0/6] x = 0, depth = 0, aim = 0, z = 0, next: forward 5
+> ",
+            output
+        );
+    }
+
+    #[test]
+    fn above_surface() {
+        let input = ["down 2", "forward 1", "up 5", "forward 3", "down 1"]
+            .into_iter()
+            .map(|s| s.parse().unwrap())
+            .collect::<Vec<Command>>();
+
+        assert_eq!(Ok((4, 1)), final_position(&input, SurfacePolicy::Clamp));
+        assert_eq!(
+            Ok((4, -2)),
+            final_position(&input, SurfacePolicy::AllowNegative)
+        );
+        assert_eq!(
+            Err(NavigationError::AboveSurface { index: 2 }),
+            final_position(&input, SurfacePolicy::Error)
+        );
+
+        // The aim becomes -3 so the last forward command goes up by 9 from a depth of 2.
+        assert_eq!(
+            Ok((4, 0, -2)),
+            final_position_with_aim(&input, SurfacePolicy::Clamp)
+        );
+        assert_eq!(
+            Ok((4, -7, -2)),
+            final_position_with_aim(&input, SurfacePolicy::AllowNegative)
+        );
+        assert_eq!(
+            Err(NavigationError::AboveSurface { index: 3 }),
+            final_position_with_aim(&input, SurfacePolicy::Error)
+        );
+    }
 }
@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+
+use super::{
+    replay::{Replay, Stepper},
+    trajectory::Trajectory,
+    Command,
+};
+
+/// What to do when a command would move the submarine above the surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfacePolicy {
+    /// Stop the submarine at the surface.
+    Clamp,
+    /// Let the depth become negative.
+    AllowNegative,
+    /// Stop navigating and return an error.
+    Error,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NavigationError {
+    /// The command at `index` moved the submarine above the surface.
+    AboveSurface { index: usize },
+    /// The command at `index` moved the submarine further than what can be represented.
+    Overflow { index: usize },
+    /// The command at `index` cannot be executed by this model.
+    Unsupported { index: usize },
+}
+
+impl std::fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AboveSurface { index } => {
+                write!(f, "command {} moved the submarine above the surface", index)
+            }
+            Self::Overflow { index } => write!(f, "command {} overflowed the position", index),
+            Self::Unsupported { index } => {
+                write!(f, "command {} is not supported by this model", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NavigationError {}
+
+/// Apply `policy` to the depth `y` reached after executing the command at `index`.
+pub fn check_depth(y: i64, index: usize, policy: SurfacePolicy) -> Result<i64, NavigationError> {
+    if y >= 0 {
+        return Ok(y);
+    }
+
+    match policy {
+        SurfacePolicy::Clamp => Ok(0),
+        SurfacePolicy::AllowNegative => Ok(y),
+        SurfacePolicy::Error => Err(NavigationError::AboveSurface { index }),
+    }
+}
+
+/// Add the magnitude of a command to a coordinate, returning `None` on overflow.
+pub fn checked_add(value: i64, delta: usize) -> Option<i64> {
+    value.checked_add(i64::try_from(delta).ok()?)
+}
+
+/// Subtract the magnitude of a command from a coordinate, returning `None` on overflow.
+pub fn checked_sub(value: i64, delta: usize) -> Option<i64> {
+    value.checked_sub(i64::try_from(delta).ok()?)
+}
+
+/// The state of the submarine after executing a command, as recorded in a trajectory.
+///
+/// Models without an aim use zero, and models with a different notion of heading can store it
+/// in `aim`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    pub x: i64,
+    pub y: i64,
+    pub aim: i64,
+    /// The lateral position, which is always zero for the planar models.
+    pub z: i64,
+}
+
+/// An interpretation of the submarine commands.
+pub trait Navigator {
+    /// The state of the submarine between commands. The default value is the initial state.
+    type State: Copy + Default + 'static;
+
+    /// Execute the command at `index`. Implementations must apply `policy` to the depth with
+    /// `check_depth`.
+    fn step(
+        &self,
+        state: Self::State,
+        command: &Command,
+        index: usize,
+        policy: SurfacePolicy,
+    ) -> Result<Self::State, NavigationError>;
+
+    /// Convert a state into a snapshot.
+    fn snapshot(&self, state: &Self::State) -> Snapshot;
+
+    /// Execute every command and return the final state.
+    fn run(
+        &self,
+        commands: &[Command],
+        policy: SurfacePolicy,
+    ) -> Result<Self::State, NavigationError> {
+        let mut state = Self::State::default();
+
+        for (index, command) in commands.iter().enumerate() {
+            state = self.step(state, command, index, policy)?;
+        }
+
+        Ok(state)
+    }
+}
+
+/// Each command moves the submarine directly.
+#[derive(Debug, Clone, Copy)]
+pub struct Plain;
+
+impl Navigator for Plain {
+    /// The horizontal position and the depth.
+    type State = (i64, i64);
+
+    fn step(
+        &self,
+        (mut x, mut y): Self::State,
+        command: &Command,
+        index: usize,
+        policy: SurfacePolicy,
+    ) -> Result<Self::State, NavigationError> {
+        let overflow = || NavigationError::Overflow { index };
+
+        match command {
+            Command::Forward(dx) => x = checked_add(x, *dx).ok_or_else(overflow)?,
+            Command::Back(dx) => x = checked_sub(x, *dx).ok_or_else(overflow)?,
+            Command::Down(dy) => y = checked_add(y, *dy).ok_or_else(overflow)?,
+            Command::Up(dy) => y = checked_sub(y, *dy).ok_or_else(overflow)?,
+            Command::Left(_) | Command::Right(_) | Command::Pitch(_) => {
+                return Err(NavigationError::Unsupported { index })
+            }
+        }
+
+        Ok((x, check_depth(y, index, policy)?))
+    }
+
+    fn snapshot(&self, &(x, y): &Self::State) -> Snapshot {
+        Snapshot { x, y, aim: 0, z: 0 }
+    }
+}
+
+/// The up and down commands change the aim of the submarine.
+///
+/// The aim can be negative, which means that moving forward decreases the depth. The policy is
+/// only applied to the depth.
+#[derive(Debug, Clone, Copy)]
+pub struct WithAim;
+
+impl Navigator for WithAim {
+    /// The horizontal position, the depth and the aim.
+    type State = (i64, i64, i64);
+
+    fn step(
+        &self,
+        (mut x, mut y, mut aim): Self::State,
+        command: &Command,
+        index: usize,
+        policy: SurfacePolicy,
+    ) -> Result<Self::State, NavigationError> {
+        let overflow = || NavigationError::Overflow { index };
+
+        match command {
+            Command::Forward(dx) => {
+                x = checked_add(x, *dx).ok_or_else(overflow)?;
+                y = i64::try_from(*dx)
+                    .ok()
+                    .and_then(|dx| aim.checked_mul(dx))
+                    .and_then(|dy| y.checked_add(dy))
+                    .ok_or_else(overflow)?;
+            }
+            // Moving back undoes moving forward, so the depth changes in the opposite direction.
+            Command::Back(dx) => {
+                x = checked_sub(x, *dx).ok_or_else(overflow)?;
+                y = i64::try_from(*dx)
+                    .ok()
+                    .and_then(|dx| aim.checked_mul(dx))
+                    .and_then(|dy| y.checked_sub(dy))
+                    .ok_or_else(overflow)?;
+            }
+            Command::Down(daim) => aim = checked_add(aim, *daim).ok_or_else(overflow)?,
+            Command::Up(daim) => aim = checked_sub(aim, *daim).ok_or_else(overflow)?,
+            Command::Left(_) | Command::Right(_) | Command::Pitch(_) => {
+                return Err(NavigationError::Unsupported { index })
+            }
+        }
+
+        Ok((x, check_depth(y, index, policy)?, aim))
+    }
+
+    fn snapshot(&self, &(x, y, aim): &Self::State) -> Snapshot {
+        Snapshot { x, y, aim, z: 0 }
+    }
+}
+
+/// Each command moves the submarine directly but there is a current that pushes the submarine
+/// `rate` units down for each unit it moves horizontally. A negative rate pushes it up.
+#[derive(Debug, Clone, Copy)]
+pub struct Drift {
+    pub rate: i64,
+}
+
+impl Navigator for Drift {
+    /// The horizontal position and the depth.
+    type State = (i64, i64);
+
+    fn step(
+        &self,
+        (x, y): Self::State,
+        command: &Command,
+        index: usize,
+        policy: SurfacePolicy,
+    ) -> Result<Self::State, NavigationError> {
+        let (x, mut y) = Plain.step((x, y), command, index, SurfacePolicy::AllowNegative)?;
+
+        // The current pushes the submarine regardless of the direction it moves.
+        if let Command::Forward(dx) | Command::Back(dx) = command {
+            y = i64::try_from(*dx)
+                .ok()
+                .and_then(|dx| self.rate.checked_mul(dx))
+                .and_then(|dy| y.checked_add(dy))
+                .ok_or(NavigationError::Overflow { index })?;
+        }
+
+        Ok((x, check_depth(y, index, policy)?))
+    }
+
+    fn snapshot(&self, &(x, y): &Self::State) -> Snapshot {
+        Snapshot { x, y, aim: 0, z: 0 }
+    }
+}
+
+/// Object-safe version of `Navigator` so models can be chosen at runtime.
+pub trait Model {
+    /// Execute every command and return the final snapshot.
+    fn final_snapshot(
+        &self,
+        commands: &[Command],
+        policy: SurfacePolicy,
+    ) -> Result<Snapshot, NavigationError>;
+
+    /// Execute every command and record the trajectory.
+    fn trajectory(
+        &self,
+        commands: &[Command],
+        policy: SurfacePolicy,
+    ) -> Result<Trajectory, NavigationError>;
+
+    /// Create a replay to step through the execution of the commands.
+    fn replay<'a>(
+        &'a self,
+        commands: &'a [Command],
+        policy: SurfacePolicy,
+    ) -> Box<dyn Stepper + 'a>;
+}
+
+impl<N: Navigator> Model for N {
+    fn final_snapshot(
+        &self,
+        commands: &[Command],
+        policy: SurfacePolicy,
+    ) -> Result<Snapshot, NavigationError> {
+        Ok(self.snapshot(&self.run(commands, policy)?))
+    }
+
+    fn trajectory(
+        &self,
+        commands: &[Command],
+        policy: SurfacePolicy,
+    ) -> Result<Trajectory, NavigationError> {
+        Trajectory::record(commands, self, policy)
+    }
+
+    fn replay<'a>(
+        &'a self,
+        commands: &'a [Command],
+        policy: SurfacePolicy,
+    ) -> Box<dyn Stepper + 'a> {
+        Box::new(Replay::new(self, commands, policy))
+    }
+}
+
+/// The models that can be chosen by name.
+pub struct Models {
+    models: BTreeMap<String, Box<dyn Model>>,
+}
+
+impl Models {
+    /// Create a registry with the `plain` and `aim` models.
+    pub fn new() -> Self {
+        let mut models = Self {
+            models: BTreeMap::new(),
+        };
+        models.register("plain", Plain);
+        models.register("aim", WithAim);
+        models
+    }
+
+    /// Register a model under `name`, replacing any model with the same name.
+    pub fn register(&mut self, name: impl Into<String>, model: impl Model + 'static) {
+        self.models.insert(name.into(), Box::new(model));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Model> {
+        self.models.get(name).map(|model| &**model)
+    }
+
+    /// The names of the registered models in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.models.keys().map(String::as_str)
+    }
+}
@@ -0,0 +1,415 @@
+use std::{collections::HashMap, fmt, num::NonZeroUsize};
+
+use super::Command;
+
+/// Error returned when there is no plan that reaches a target.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanError {
+    /// The plain model cannot change the aim.
+    AimUnreachable,
+    /// The target is too far to compute the plan without overflowing.
+    OutOfRange,
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AimUnreachable => write!(f, "the plain model cannot change the aim"),
+            Self::OutOfRange => write!(f, "the target is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// Push the commands that change a value from `from` to `to`, using `increase` or `decrease`
+/// with magnitudes of at most `max`.
+fn push_change(
+    commands: &mut Vec<Command>,
+    from: i64,
+    to: i64,
+    max: NonZeroUsize,
+    increase: fn(usize) -> Command,
+    decrease: fn(usize) -> Command,
+) {
+    let command = if to > from { increase } else { decrease };
+    let mut remaining = to.abs_diff(from);
+
+    while remaining > 0 {
+        let magnitude = remaining.min(max.get() as u64);
+        commands.push(command(magnitude as usize));
+        remaining -= magnitude;
+    }
+}
+
+/// Find the shortest list of commands that reaches `(x, depth)` when each command moves the
+/// submarine directly.
+///
+/// The aim never changes in this model, so `aim` can only be `None` or `Some(0)`. The depth
+/// changes monotonically, so the plan never goes above the surface if `depth` is not negative.
+pub fn plan_plain(
+    (x, depth): (i64, i64),
+    aim: Option<i64>,
+    max: NonZeroUsize,
+) -> Result<Vec<Command>, PlanError> {
+    if aim.is_some_and(|aim| aim != 0) {
+        return Err(PlanError::AimUnreachable);
+    }
+
+    // Each command changes a single coordinate, so the plan cannot be shorter than this.
+    let mut commands = Vec::new();
+    push_change(&mut commands, 0, x, max, Command::Forward, Command::Back);
+    push_change(&mut commands, 0, depth, max, Command::Down, Command::Up);
+
+    Ok(commands)
+}
+
+/// The position, depth and aim of the submarine during a search.
+type State = (i64, i64, i64);
+
+/// A kind of command used by the aim model, whether it moves the submarine and the sign of the
+/// change.
+type Kind = (fn(usize) -> Command, bool, i64);
+
+const KINDS: [Kind; 4] = [
+    (Command::Forward, true, 1),
+    (Command::Back, true, -1),
+    (Command::Down, false, 1),
+    (Command::Up, false, -1),
+];
+
+/// The number of states `plan_with_aim` visits before it settles for the cruising plan.
+const MAX_NODES: usize = 1 << 18;
+
+/// Depth-first search of the plans with a bounded amount of commands under the aim model.
+struct Search {
+    target: (i64, i64),
+    aim: Option<i64>,
+    /// The largest magnitude of a command.
+    max: i64,
+    /// The index in `KINDS` and the magnitude of each command of the current plan.
+    commands: Vec<(usize, i64)>,
+    /// The largest budget known to be too small for each state and last command, as the index
+    /// in `KINDS` and whether it was as large as possible.
+    failed: HashMap<(State, Option<(usize, bool)>), usize>,
+    /// The number of states that can still be visited.
+    nodes: usize,
+}
+
+impl Search {
+    fn reached(&self, (x, depth, aim): State) -> bool {
+        (x, depth) == self.target && self.aim.is_none_or(|target| target == aim)
+    }
+
+    /// A lower bound of the number of commands needed to reach the target from `state`, or
+    /// `limit + 1` if that is larger than `limit`.
+    fn lower_bound(&self, (x, depth, aim): State, limit: usize) -> usize {
+        let max = self.max as u128;
+        let dx = u128::from(self.target.0.abs_diff(x));
+        let dd = u128::from(self.target.1.abs_diff(depth));
+        let aim_abs = u128::from(aim.unsigned_abs());
+
+        // The depth can only change while moving.
+        let mut moves = dx.div_ceil(max);
+        if dd > 0 {
+            moves = moves.max(1);
+        }
+        let turns = u128::from(self.aim.map_or(0, |target| target.abs_diff(aim))).div_ceil(max);
+
+        let mut len = moves + turns;
+        if dd == 0 {
+            return len.min(limit as u128 + 1) as usize;
+        }
+
+        // With `len - m` moves and `m` changes of aim the depth changes at most
+        // `(len - m) * max * (|aim| + m * max)`, which is concave in `m`.
+        while len <= limit as u128 {
+            let (low, high) = (turns, len - moves);
+            let peak = (len * max).saturating_sub(aim_abs) / (2 * max);
+            let reach = [
+                low,
+                high,
+                peak.clamp(low, high),
+                (peak + 1).clamp(low, high),
+            ]
+            .into_iter()
+            .map(|m| {
+                (len - m)
+                    .saturating_mul(max)
+                    .saturating_mul(aim_abs.saturating_add(m.saturating_mul(max)))
+            })
+            .max()
+            .unwrap_or(0);
+
+            if reach >= dd {
+                return len as usize;
+            }
+            len += 1;
+        }
+
+        limit + 1
+    }
+
+    /// The signed changes worth trying for a command of the given kind when at most two commands
+    /// are left.
+    ///
+    /// The last command must reach the target exactly. Consecutive commands in the same
+    /// direction only make sense if the first one is as large as possible, so the other command
+    /// before the last one must reach the target in one coordinate or set the aim needed to
+    /// reach the depth in one move.
+    fn last_changes(&self, (x, depth, aim): State, moves: bool, sign: i64) -> Vec<i64> {
+        let mut changes = vec![Some(sign * self.max)];
+
+        if moves {
+            changes.push(self.target.0.checked_sub(x));
+        } else {
+            changes.push(self.aim.and_then(|target| target.checked_sub(aim)));
+            let dx = self.target.0.checked_sub(x);
+            let dd = self.target.1.checked_sub(depth);
+            if let (Some(dx), Some(dd)) = (dx, dd) {
+                if dx != 0 && dd % dx == 0 {
+                    changes.push((dd / dx).checked_sub(aim));
+                }
+            }
+        }
+
+        let mut changes = changes
+            .into_iter()
+            .flatten()
+            .filter(|change| change.signum() == sign && change.abs() <= self.max)
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|change| std::cmp::Reverse(change.abs()));
+        changes.dedup();
+        changes
+    }
+
+    /// Check if the target can be reached from `state` with at most `budget` commands, leaving
+    /// the commands in `self.commands` if it can.
+    fn search(&mut self, state: State, budget: usize) -> bool {
+        let Some(nodes) = self.nodes.checked_sub(1) else {
+            return false;
+        };
+        self.nodes = nodes;
+
+        if self.reached(state) {
+            return true;
+        }
+        if self.lower_bound(state, budget) > budget {
+            return false;
+        }
+
+        let previous = self.commands.last().copied();
+        let key = (
+            state,
+            previous.map(|(kind, magnitude)| (kind, magnitude == self.max)),
+        );
+        if self
+            .failed
+            .get(&key)
+            .is_some_and(|&failed| failed >= budget)
+        {
+            return false;
+        }
+
+        let (x, depth, aim) = state;
+
+        for (kind, &(_, moves, sign)) in KINDS.iter().enumerate() {
+            // Commands in opposite directions can be merged into one, and so can commands in the
+            // same direction unless the first one is as large as possible.
+            if let Some((previous, magnitude)) = previous {
+                let (_, previous_moves, previous_sign) = KINDS[previous];
+                if previous_moves == moves && (previous_sign != sign || magnitude < self.max) {
+                    continue;
+                }
+            }
+
+            let changes: Box<dyn Iterator<Item = i64>> = if budget <= 2 {
+                Box::new(self.last_changes(state, moves, sign).into_iter())
+            } else {
+                Box::new((1..=self.max).rev().map(move |magnitude| sign * magnitude))
+            };
+
+            for change in changes {
+                let next = if moves {
+                    x.checked_add(change)
+                        .zip(aim.checked_mul(change).and_then(|dy| depth.checked_add(dy)))
+                        .map(|(x, depth)| (x, depth, aim))
+                } else {
+                    aim.checked_add(change).map(|aim| (x, depth, aim))
+                };
+
+                let Some(next) = next else {
+                    continue;
+                };
+
+                self.commands.push((kind, change.abs()));
+                if self.search(next, budget - 1) {
+                    return true;
+                }
+                self.commands.pop();
+
+                // The search gave up, so this state did not really fail.
+                if self.nodes == 0 {
+                    return false;
+                }
+            }
+        }
+
+        self.failed.insert(key, budget);
+        false
+    }
+}
+
+/// Find the shortest list of commands that reaches `(x, depth)` and optionally `aim` when the up
+/// and down commands change the aim of the submarine.
+///
+/// The commands are no larger than `max` nor than `|x| + |depth| + |aim| + 1`. The search takes
+/// exponential time in the length of the plan, so it gives up after visiting `MAX_NODES` states
+/// and returns the cruising plan instead, which may be longer. This only happens for waypoints
+/// that are many times `max` away.
+pub fn plan_with_aim(
+    (x, depth): (i64, i64),
+    aim: Option<i64>,
+    max: NonZeroUsize,
+) -> Result<Vec<Command>, PlanError> {
+    let limit = x
+        .unsigned_abs()
+        .checked_add(depth.unsigned_abs())
+        .and_then(|sum| sum.checked_add(aim.map_or(0, i64::unsigned_abs)))
+        .and_then(|sum| sum.checked_add(1))
+        .and_then(|sum| i64::try_from(sum).ok())
+        .ok_or(PlanError::OutOfRange)?;
+
+    // The cruising plan bounds the length of the shortest plan.
+    let upper = cruise_plan((x, depth), aim, max)?;
+
+    let mut search = Search {
+        target: (x, depth),
+        aim,
+        max: i64::try_from(max.get()).unwrap_or(i64::MAX).min(limit),
+        commands: Vec::new(),
+        failed: HashMap::new(),
+        nodes: MAX_NODES,
+    };
+
+    // Search with increasing budgets so the first plan found is the shortest one.
+    let start = search.lower_bound((0, 0, 0), upper.len());
+    for budget in start..upper.len() {
+        if search.search((0, 0, 0), budget) {
+            let commands = search
+                .commands
+                .into_iter()
+                .map(|(kind, magnitude)| KINDS[kind].0(magnitude as usize))
+                .collect();
+            return Ok(commands);
+        }
+        if search.nodes == 0 {
+            break;
+        }
+    }
+
+    Ok(upper)
+}
+
+/// Find a list of commands that reaches `(x, depth)` and optionally `aim` when the up and down
+/// commands change the aim of the submarine.
+///
+/// The submarine cruises at one aim or at two consecutive aims, and the shortest of those plans
+/// is returned. This is quick to compute but it is not always the shortest plan, so it is only
+/// used as an upper bound for `plan_with_aim`.
+fn cruise_plan(
+    (x, depth): (i64, i64),
+    aim: Option<i64>,
+    max: NonZeroUsize,
+) -> Result<Vec<Command>, PlanError> {
+    if x < 0 {
+        // Moving back with the opposite aim changes the depth in the same way as moving forward,
+        // so the plan for the mirrored target can be mirrored back.
+        let x = x.checked_neg().ok_or(PlanError::OutOfRange)?;
+        let aim = aim
+            .map(|aim| aim.checked_neg().ok_or(PlanError::OutOfRange))
+            .transpose()?;
+
+        let commands = cruise_plan((x, depth), aim, max)?
+            .into_iter()
+            .map(|command| match command {
+                Command::Forward(value) => Command::Back(value),
+                Command::Back(value) => Command::Forward(value),
+                Command::Down(value) => Command::Up(value),
+                Command::Up(value) => Command::Down(value),
+                // The planner never turns the submarine.
+                command => command,
+            })
+            .collect();
+
+        return Ok(commands);
+    }
+
+    if x == 0 {
+        if depth == 0 {
+            let mut commands = Vec::new();
+            push_change(
+                &mut commands,
+                0,
+                aim.unwrap_or(0),
+                max,
+                Command::Down,
+                Command::Up,
+            );
+            return Ok(commands);
+        }
+
+        // The depth can only change while moving, so move back one unit and then plan the way
+        // back to `x = 0`.
+        let mut commands = vec![Command::Back(1)];
+        commands.extend(cruise_plan((1, depth), aim, max)?);
+        return Ok(commands);
+    }
+
+    // Cruise `x - r` units at aim `q` and `r` units at aim `q + 1`, in any order, so the depth is
+    // `q * (x - r) + (q + 1) * r = q * x + r = depth`. If `r` is not zero then `x > 1` so
+    // `q + 1` cannot overflow.
+    let (q, r) = (depth.div_euclid(x), depth.rem_euclid(x));
+    let candidates = if r == 0 {
+        vec![vec![(q, x)]]
+    } else {
+        vec![vec![(q, x - r), (q + 1, r)], vec![(q + 1, r), (q, x - r)]]
+    };
+
+    let commands = candidates
+        .into_iter()
+        .map(|segments| {
+            let mut commands = Vec::new();
+            let mut current = 0;
+
+            for (next, distance) in segments {
+                push_change(
+                    &mut commands,
+                    current,
+                    next,
+                    max,
+                    Command::Down,
+                    Command::Up,
+                );
+                push_change(
+                    &mut commands,
+                    0,
+                    distance,
+                    max,
+                    Command::Forward,
+                    Command::Back,
+                );
+                current = next;
+            }
+
+            if let Some(aim) = aim {
+                push_change(&mut commands, current, aim, max, Command::Down, Command::Up);
+            }
+
+            commands
+        })
+        .min_by_key(Vec::len)
+        .unwrap_or_default();
+
+    Ok(commands)
+}
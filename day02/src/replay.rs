@@ -0,0 +1,255 @@
+use std::io::{self, BufRead, Write};
+
+use super::{
+    navigator::{NavigationError, Navigator, Snapshot, SurfacePolicy},
+    Command,
+};
+
+/// Object-safe interface to move through the execution of a list of commands.
+pub trait Stepper {
+    /// The number of commands executed so far, which is also the index of the next command.
+    fn position(&self) -> usize;
+
+    /// The commands being executed.
+    fn commands(&self) -> &[Command];
+
+    /// The state after executing `position()` commands.
+    fn snapshot(&self) -> Snapshot;
+
+    /// Execute the next command. Returns `Ok(false)` if all the commands were executed.
+    fn forward(&mut self) -> Result<bool, NavigationError>;
+
+    /// Undo the last command. Returns `false` if no command has been executed.
+    fn back(&mut self) -> bool;
+}
+
+/// Replay the execution of `commands` with a navigator.
+pub struct Replay<'a, N: Navigator> {
+    navigator: &'a N,
+    commands: &'a [Command],
+    policy: SurfacePolicy,
+    /// The `i`th state is the one after executing `i` commands. States are kept when stepping
+    /// back so stepping forward again does not execute the commands again.
+    states: Vec<N::State>,
+    position: usize,
+}
+
+impl<'a, N: Navigator> Replay<'a, N> {
+    pub fn new(navigator: &'a N, commands: &'a [Command], policy: SurfacePolicy) -> Self {
+        Self {
+            navigator,
+            commands,
+            policy,
+            states: vec![N::State::default()],
+            position: 0,
+        }
+    }
+}
+
+impl<N: Navigator> Stepper for Replay<'_, N> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn commands(&self) -> &[Command] {
+        self.commands
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.navigator.snapshot(&self.states[self.position])
+    }
+
+    fn forward(&mut self) -> Result<bool, NavigationError> {
+        let Some(command) = self.commands.get(self.position) else {
+            return Ok(false);
+        };
+
+        if self.position + 1 == self.states.len() {
+            let state = self.states[self.position];
+            let state = self
+                .navigator
+                .step(state, command, self.position, self.policy)?;
+            self.states.push(state);
+        }
+        self.position += 1;
+
+        Ok(true)
+    }
+
+    fn back(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.position -= 1;
+        true
+    }
+}
+
+/// A condition that stops the replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Stop before executing the command at this index.
+    Index(usize),
+    /// Stop when the depth crosses this value in either direction.
+    Depth(i64),
+}
+
+impl Breakpoint {
+    /// Check if the breakpoint is hit after moving from the state `previous` to `stepper`.
+    fn is_hit(&self, previous: &Snapshot, stepper: &dyn Stepper) -> bool {
+        match *self {
+            Self::Index(index) => stepper.position() == index,
+            Self::Depth(depth) => {
+                let current = stepper.snapshot();
+                (previous.y < depth) != (current.y < depth)
+            }
+        }
+    }
+}
+
+/// Step forward, or backward if `reverse` is `true`, until a breakpoint is hit or there are no
+/// more commands. Returns the breakpoint that was hit, if any.
+pub fn resume(
+    stepper: &mut dyn Stepper,
+    breakpoints: &[Breakpoint],
+    reverse: bool,
+) -> Result<Option<Breakpoint>, NavigationError> {
+    loop {
+        let previous = stepper.snapshot();
+        let moved = if reverse {
+            stepper.back()
+        } else {
+            stepper.forward()?
+        };
+
+        if !moved {
+            return Ok(None);
+        }
+
+        if let Some(breakpoint) = breakpoints
+            .iter()
+            .find(|breakpoint| breakpoint.is_hit(&previous, stepper))
+        {
+            return Ok(Some(*breakpoint));
+        }
+    }
+}
+
+/// Write the current state of `stepper` and the next command in a single line.
+pub fn print_state(stepper: &dyn Stepper, output: &mut dyn Write) -> io::Result<()> {
+    let snapshot = stepper.snapshot();
+    let position = stepper.position();
+
+    write!(
+        output,
+        "[{}/{}] x = {}, depth = {}, aim = {}, z = {}",
+        position,
+        stepper.commands().len(),
+        snapshot.x,
+        snapshot.y,
+        snapshot.aim,
+        snapshot.z
+    )?;
+
+    match stepper.commands().get(position) {
+        Some(command) => writeln!(output, ", next: {}", command),
+        None => writeln!(output, ", done"),
+    }
+}
+
+/// Execute every command writing the state after each one.
+pub fn trace(stepper: &mut dyn Stepper, output: &mut dyn Write) -> Result<(), anyhow::Error> {
+    print_state(stepper, output)?;
+    while stepper.forward()? {
+        print_state(stepper, output)?;
+    }
+
+    Ok(())
+}
+
+const HELP: &str = "\
+commands:
+  step [n]        execute the next n commands
+  back [n]        undo the last n commands
+  continue        execute commands until a breakpoint is hit
+  reverse         undo commands until a breakpoint is hit
+  break <index>   stop before executing the command at index
+  depth <depth>   stop when the depth crosses depth
+  delete          remove every breakpoint
+  print           print the current state
+  quit            stop debugging
+";
+
+/// Run an interactive debugging session reading debugger commands from `input`.
+pub fn debug(
+    stepper: &mut dyn Stepper,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<(), anyhow::Error> {
+    let mut breakpoints = Vec::new();
+    print_state(stepper, output)?;
+
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let argument = words.next().map(str::parse::<i64>).transpose();
+
+        let result = match (command, argument) {
+            (_, Err(err)) => {
+                writeln!(output, "invalid argument: {}", err)?;
+                continue;
+            }
+            // Stop at either end of the commands so huge counts return right away.
+            ("s" | "step", Ok(count)) => (0..count.unwrap_or(1))
+                .map(|_| stepper.forward())
+                .find(|moved| *moved != Ok(true))
+                .unwrap_or(Ok(true))
+                .map(|_| None),
+            ("b" | "back", Ok(count)) => {
+                for _ in 0..count.unwrap_or(1) {
+                    if !stepper.back() {
+                        break;
+                    }
+                }
+                Ok(None)
+            }
+            ("c" | "continue", Ok(_)) => resume(stepper, &breakpoints, false),
+            ("r" | "reverse", Ok(_)) => resume(stepper, &breakpoints, true),
+            ("break", Ok(Some(index))) if index >= 0 => {
+                breakpoints.push(Breakpoint::Index(index as usize));
+                continue;
+            }
+            ("depth", Ok(Some(depth))) => {
+                breakpoints.push(Breakpoint::Depth(depth));
+                continue;
+            }
+            ("delete", Ok(_)) => {
+                breakpoints.clear();
+                continue;
+            }
+            ("p" | "print", Ok(_)) => Ok(None),
+            ("q" | "quit", Ok(_)) => return Ok(()),
+            _ => {
+                write!(output, "{}", HELP)?;
+                continue;
+            }
+        };
+
+        match result {
+            Ok(Some(breakpoint)) => writeln!(output, "hit {:?}", breakpoint)?,
+            Ok(None) => {}
+            Err(err) => writeln!(output, "error: {}", err)?,
+        }
+        print_state(stepper, output)?;
+    }
+}
@@ -0,0 +1,222 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use super::{Command, CommandParseError};
+
+/// The largest amount of commands a script can expand to.
+const MAX_COMMANDS: usize = 1 << 20;
+
+/// A word or brace in a script.
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    /// The 1-based line of the token.
+    line: usize,
+    /// The 1-based column of the first character of the token.
+    column: usize,
+}
+
+/// Error returned when a script cannot be parsed.
+#[derive(Debug)]
+pub struct ScriptParseError {
+    /// The 1-based line where the error was found.
+    pub line: usize,
+    /// The 1-based column where the error was found.
+    pub column: usize,
+    pub error: CommandParseError,
+}
+
+impl fmt::Display for ScriptParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.error
+        )
+    }
+}
+
+impl std::error::Error for ScriptParseError {}
+
+/// Split `input` into tokens, skipping whitespace and comments.
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        // Everything after a `#` is a comment.
+        let line = line.split_once('#').map_or(line, |(code, _)| code);
+        let mut chars = line.char_indices().enumerate().peekable();
+
+        while let Some((column, (start, c))) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+
+            let mut end = start + c.len_utf8();
+            // Braces are tokens on their own, any other token ends at a brace or whitespace.
+            if c != '{' && c != '}' {
+                while let Some(&(_, (i, c))) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+            }
+
+            tokens.push(Token {
+                text: &line[start..end],
+                line: index + 1,
+                column: column + 1,
+            });
+        }
+    }
+
+    tokens
+}
+
+/// Recursive descent parser over the tokens of a script.
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    /// The commands of each macro defined so far.
+    macros: HashMap<&'a str, Vec<Command>>,
+    /// The position reported for errors at the end of the input.
+    end: (usize, usize),
+}
+
+impl<'a> Parser<'a> {
+    fn error(token: Token<'_>, error: CommandParseError) -> ScriptParseError {
+        ScriptParseError {
+            line: token.line,
+            column: token.column,
+            error,
+        }
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, ScriptParseError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .copied()
+            .ok_or(ScriptParseError {
+                line: self.end.0,
+                column: self.end.1,
+                error: CommandParseError::UnexpectedEnd,
+            })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn number(&mut self) -> Result<usize, ScriptParseError> {
+        let token = self.next()?;
+        usize::from_str(token.text).map_err(|err| Self::error(token, err.into()))
+    }
+
+    fn open_brace(&mut self) -> Result<(), ScriptParseError> {
+        let token = self.next()?;
+        if token.text != "{" {
+            return Err(Self::error(token, CommandParseError::ExpectedBrace));
+        }
+        Ok(())
+    }
+
+    /// Check that adding `len` commands to `commands` does not exceed `MAX_COMMANDS`.
+    fn check_len(commands: &[Command], len: usize) -> Option<usize> {
+        commands
+            .len()
+            .checked_add(len)
+            .filter(|&total| total <= MAX_COMMANDS)
+    }
+
+    /// Parse statements until the end of the input or until a closing brace if `nested` is
+    /// `true`.
+    fn block(&mut self, nested: bool) -> Result<Vec<Command>, ScriptParseError> {
+        let mut commands = Vec::new();
+
+        loop {
+            let token = match self.tokens.get(self.position) {
+                Some(&token) => token,
+                None if nested => return self.next().map(|_| commands),
+                None => return Ok(commands),
+            };
+            self.position += 1;
+
+            match token.text {
+                "}" if nested => return Ok(commands),
+                "}" => return Err(Self::error(token, CommandParseError::UnmatchedBrace)),
+                "{" => return Err(Self::error(token, CommandParseError::UnexpectedBrace)),
+                "forward" => commands.push(Command::Forward(self.number()?)),
+                "back" => commands.push(Command::Back(self.number()?)),
+                "down" => commands.push(Command::Down(self.number()?)),
+                "up" => commands.push(Command::Up(self.number()?)),
+                "left" => commands.push(Command::Left(self.number()?)),
+                "right" => commands.push(Command::Right(self.number()?)),
+                "pitch" => commands.push(Command::Pitch(self.number()?)),
+                "repeat" => {
+                    let times = self.number()?;
+                    self.open_brace()?;
+                    let body = self.block(true)?;
+                    // Check the length before expanding the body so huge counts do not exhaust
+                    // the memory.
+                    body.len()
+                        .checked_mul(times)
+                        .and_then(|len| Self::check_len(&commands, len))
+                        .ok_or(Self::error(token, CommandParseError::TooManyCommands))?;
+                    for _ in 0..times {
+                        commands.extend_from_slice(&body);
+                    }
+                }
+                "macro" => {
+                    let name = self.next()?;
+                    if is_keyword(name.text) || name.text == "{" || name.text == "}" {
+                        return Err(Self::error(name, CommandParseError::InvalidMacroName));
+                    }
+                    self.open_brace()?;
+                    let body = self.block(true)?;
+                    self.macros.insert(name.text, body);
+                }
+                name => match self.macros.get(name) {
+                    Some(body) => {
+                        Self::check_len(&commands, body.len())
+                            .ok_or(Self::error(token, CommandParseError::TooManyCommands))?;
+                        commands.extend_from_slice(body);
+                    }
+                    None => return Err(Self::error(token, CommandParseError::UnknownCommand)),
+                },
+            }
+        }
+    }
+}
+
+/// Check if `word` has a meaning in the script language.
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "forward" | "back" | "down" | "up" | "left" | "right" | "pitch" | "repeat" | "macro"
+    )
+}
+
+/// Parse a script into the list of commands it expands to.
+///
+/// Besides the `forward`, `back`, `down`, `up`, `left`, `right` and `pitch` commands, scripts
+/// can have `#` comments, `repeat <n> { ... }` blocks and macros defined with
+/// `macro <name> { ... }` and expanded by writing their name. Macros must be defined before they
+/// are used. Scripts cannot expand to more than 2^20 commands.
+pub fn parse_script(input: &str) -> Result<Vec<Command>, ScriptParseError> {
+    let end = input
+        .lines()
+        .enumerate()
+        .last()
+        .map_or((1, 1), |(index, line)| {
+            (index + 1, line.chars().count() + 1)
+        });
+
+    let mut parser = Parser {
+        tokens: tokenize(input),
+        position: 0,
+        macros: HashMap::new(),
+        end,
+    };
+
+    parser.block(false)
+}
@@ -0,0 +1,135 @@
+use super::{
+    navigator::{NavigationError, Navigator, Snapshot, SurfacePolicy},
+    Command,
+};
+
+/// The state of a submarine moving in three dimensions.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SpatialState {
+    /// The position along the initial heading.
+    pub x: f64,
+    pub depth: f64,
+    /// The position to the right of the initial heading.
+    pub z: f64,
+    /// The heading in degrees, clockwise from the initial heading.
+    pub heading: u32,
+    /// The pitch in degrees, positive when the nose points down.
+    pub pitch: u32,
+    /// The aim, which is only used if the model has aim.
+    pub aim: i64,
+}
+
+/// The submarine moves in three dimensions following its heading and pitch.
+///
+/// `left`, `right` and `pitch` turn the submarine and `forward` and `back` move it along the
+/// direction it is pointing. `down` and `up` move the submarine directly, or change its aim if
+/// `aim` is `true`, in which case moving also changes the depth by the aim times the horizontal
+/// distance. Without any turn this model gives the same x and depth as the planar models.
+#[derive(Debug, Clone, Copy)]
+pub struct Spatial {
+    pub aim: bool,
+}
+
+/// Add `degrees` to `angle` and wrap the result to a full turn.
+fn turn(angle: u32, degrees: usize) -> u32 {
+    // The remainder is always smaller than 360 so it fits in a `u32`.
+    (angle + (degrees % 360) as u32) % 360
+}
+
+impl Spatial {
+    /// Move the submarine down by `value`, or change its aim if the model has aim. `None` means
+    /// that the value does not fit in an `i64`.
+    fn change_depth(
+        &self,
+        state: &mut SpatialState,
+        value: Option<i64>,
+        index: usize,
+    ) -> Result<(), NavigationError> {
+        let value = value.ok_or(NavigationError::Overflow { index })?;
+
+        if self.aim {
+            state.aim = state
+                .aim
+                .checked_add(value)
+                .ok_or(NavigationError::Overflow { index })?;
+        } else {
+            state.depth += value as f64;
+        }
+
+        Ok(())
+    }
+}
+
+impl Navigator for Spatial {
+    type State = SpatialState;
+
+    fn step(
+        &self,
+        mut state: Self::State,
+        command: &Command,
+        index: usize,
+        policy: SurfacePolicy,
+    ) -> Result<Self::State, NavigationError> {
+        let distance = match *command {
+            Command::Forward(distance) => distance as f64,
+            Command::Back(distance) => -(distance as f64),
+            Command::Left(degrees) => {
+                state.heading = turn(state.heading, 360 - degrees % 360);
+                0.0
+            }
+            Command::Right(degrees) => {
+                state.heading = turn(state.heading, degrees);
+                0.0
+            }
+            Command::Pitch(degrees) => {
+                state.pitch = turn(state.pitch, degrees);
+                0.0
+            }
+            Command::Down(value) => {
+                self.change_depth(&mut state, i64::try_from(value).ok(), index)?;
+                0.0
+            }
+            Command::Up(value) => {
+                let value = i64::try_from(value).ok().map(|value| -value);
+                self.change_depth(&mut state, value, index)?;
+                0.0
+            }
+        };
+
+        let (heading, pitch) = (
+            f64::from(state.heading).to_radians(),
+            f64::from(state.pitch).to_radians(),
+        );
+        let horizontal = distance * pitch.cos();
+
+        state.x += horizontal * heading.cos();
+        state.z += horizontal * heading.sin();
+        state.depth += distance * pitch.sin() + state.aim as f64 * horizontal;
+
+        if ![state.x, state.depth, state.z]
+            .iter()
+            .all(|value| value.is_finite())
+        {
+            return Err(NavigationError::Overflow { index });
+        }
+
+        if state.depth < 0.0 {
+            match policy {
+                SurfacePolicy::Clamp => state.depth = 0.0,
+                SurfacePolicy::AllowNegative => {}
+                SurfacePolicy::Error => return Err(NavigationError::AboveSurface { index }),
+            }
+        }
+
+        Ok(state)
+    }
+
+    fn snapshot(&self, state: &Self::State) -> Snapshot {
+        Snapshot {
+            x: state.x.round() as i64,
+            y: state.depth.round() as i64,
+            aim: state.aim,
+            z: state.z.round() as i64,
+        }
+    }
+}
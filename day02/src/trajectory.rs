@@ -0,0 +1,152 @@
+use std::fmt::{self, Write};
+
+use super::{
+    navigator::{NavigationError, Navigator, Snapshot, SurfacePolicy},
+    Command,
+};
+
+/// The state of the submarine after executing each command.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Trajectory {
+    /// The `i`th snapshot is the state after executing the `i`th command. The initial state is
+    /// not included.
+    pub snapshots: Vec<Snapshot>,
+}
+
+/// Statistics about a trajectory.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub commands: usize,
+    /// The maximum depth reached and the index of the first command that reached it.
+    pub max_depth: (i64, usize),
+    /// The minimum depth reached and the index of the first command that reached it.
+    pub min_depth: (i64, usize),
+    pub last: Snapshot,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "commands:  {}", self.commands)?;
+        writeln!(
+            f,
+            "max depth: {} (command {})",
+            self.max_depth.0, self.max_depth.1
+        )?;
+        writeln!(
+            f,
+            "min depth: {} (command {})",
+            self.min_depth.0, self.min_depth.1
+        )?;
+        writeln!(
+            f,
+            "final:     x = {}, depth = {}, aim = {}",
+            self.last.x, self.last.y, self.last.aim
+        )
+    }
+}
+
+impl Trajectory {
+    /// Execute `commands` using `navigator` and record the state after each one.
+    pub fn record<N: Navigator + ?Sized>(
+        commands: &[Command],
+        navigator: &N,
+        policy: SurfacePolicy,
+    ) -> Result<Self, NavigationError> {
+        let mut snapshots = Vec::with_capacity(commands.len());
+        let mut state = N::State::default();
+
+        for (index, command) in commands.iter().enumerate() {
+            state = navigator.step(state, command, index, policy)?;
+            snapshots.push(navigator.snapshot(&state));
+        }
+
+        Ok(Self { snapshots })
+    }
+
+    /// Compute the summary of the trajectory or return `None` if it is empty.
+    pub fn summary(&self) -> Option<Summary> {
+        let mut snapshots = self.snapshots.iter().enumerate();
+        let (_, first) = snapshots.next()?;
+
+        let mut max_depth = (first.y, 0);
+        let mut min_depth = (first.y, 0);
+        let mut last = *first;
+
+        for (index, snapshot) in snapshots {
+            if snapshot.y > max_depth.0 {
+                max_depth = (snapshot.y, index);
+            }
+            if snapshot.y < min_depth.0 {
+                min_depth = (snapshot.y, index);
+            }
+            last = *snapshot;
+        }
+
+        Some(Summary {
+            commands: self.snapshots.len(),
+            max_depth,
+            min_depth,
+            last,
+        })
+    }
+
+    /// Render the trajectory as CSV with one row per command.
+    pub fn to_csv(&self, commands: &[Command]) -> String {
+        let mut csv = String::from("index,command,value,x,depth,aim,z\n");
+
+        for (index, (command, snapshot)) in commands.iter().zip(&self.snapshots).enumerate() {
+            // Writing to a `String` cannot fail.
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                index,
+                command.name(),
+                command.value(),
+                snapshot.x,
+                snapshot.y,
+                snapshot.aim,
+                snapshot.z
+            )
+            .unwrap();
+        }
+
+        csv
+    }
+
+    /// Render the trajectory as an SVG polyline starting at the origin. The depth grows
+    /// downwards.
+    pub fn to_svg(&self) -> String {
+        let origin = Snapshot::default();
+        let points = Some(&origin).into_iter().chain(&self.snapshots);
+
+        let (mut min_x, mut max_x, mut min_y, mut max_y) = (0, 0, 0, 0);
+        let mut polyline = String::new();
+
+        for snapshot in points {
+            min_x = min_x.min(snapshot.x);
+            max_x = max_x.max(snapshot.x);
+            min_y = min_y.min(snapshot.y);
+            max_y = max_y.max(snapshot.y);
+
+            if !polyline.is_empty() {
+                polyline.push(' ');
+            }
+            write!(polyline, "{},{}", snapshot.x, snapshot.y).unwrap();
+        }
+
+        // Make sure the view box is never empty.
+        let width = (max_x - min_x).max(1);
+        let height = (max_y - min_y).max(1);
+
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" ",
+                "preserveAspectRatio=\"none\">\n",
+                "  <polyline points=\"{}\" fill=\"none\" stroke=\"black\" ",
+                "vector-effect=\"non-scaling-stroke\"/>\n",
+                "</svg>\n"
+            ),
+            min_x, min_y, width, height, polyline
+        )
+    }
+}
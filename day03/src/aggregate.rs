@@ -0,0 +1,123 @@
+use std::{collections::VecDeque, fmt, num::NonZeroUsize};
+
+use super::{compute_rates, Bits, Rates, TieError, TiePolicy};
+
+/// Error returned when retracting a number that was not added.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RetractError;
+
+impl fmt::Display for RetractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the number to retract was not added")
+    }
+}
+
+impl std::error::Error for RetractError {}
+
+/// Keeps the number of one-digits in each position up to date as numbers are added and
+/// retracted, so the rates can be computed at any time without the numbers.
+#[derive(Debug)]
+pub struct Aggregator<B> {
+    one_counts: Vec<usize>,
+    len: usize,
+    /// The largest amount of numbers to keep, if any.
+    window: Option<NonZeroUsize>,
+    /// The numbers in the window, starting with the oldest one. This is empty if there is no
+    /// window.
+    numbers: VecDeque<B>,
+}
+
+impl<B: Bits + PartialEq> Aggregator<B> {
+    /// Create an aggregator for numbers with `digits` digits that keeps every number.
+    pub fn new(digits: usize) -> Self {
+        Self {
+            one_counts: vec![0; digits],
+            len: 0,
+            window: None,
+            numbers: VecDeque::new(),
+        }
+    }
+
+    /// Create an aggregator for numbers with `digits` digits that only keeps the last `window`
+    /// numbers.
+    pub fn with_window(digits: usize, window: NonZeroUsize) -> Self {
+        Self {
+            window: Some(window),
+            numbers: VecDeque::with_capacity(window.get()),
+            ..Self::new(digits)
+        }
+    }
+
+    /// The number of one-digits in each position.
+    pub fn one_counts(&self) -> &[usize] {
+        &self.one_counts
+    }
+
+    /// The amount of numbers that are aggregated.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Add a number, dropping the oldest one if the window is full.
+    pub fn push(&mut self, number: B) {
+        if let Some(window) = self.window {
+            if self.numbers.len() == window.get() {
+                if let Some(oldest) = self.numbers.pop_front() {
+                    self.remove_counts(&oldest);
+                }
+            }
+            self.numbers.push_back(number.clone());
+        }
+
+        for (pos, one_count) in self.one_counts.iter_mut().enumerate() {
+            if number.bit(pos) {
+                *one_count += 1;
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Retract a number that was added before.
+    ///
+    /// If there is a window, the newest number in the window that is equal to `number` is
+    /// removed. Otherwise the numbers are not kept so this only checks that retracting `number`
+    /// leaves every count of ones between zero and the new amount of numbers.
+    pub fn retract(&mut self, number: &B) -> Result<(), RetractError> {
+        if self.window.is_some() {
+            let index = self
+                .numbers
+                .iter()
+                .rposition(|other| other == number)
+                .ok_or(RetractError)?;
+            self.numbers.remove(index);
+        } else if self.len == 0
+            || self.one_counts.iter().enumerate().any(|(pos, &one_count)| {
+                if number.bit(pos) {
+                    one_count == 0
+                } else {
+                    one_count == self.len
+                }
+            })
+        {
+            return Err(RetractError);
+        }
+
+        self.remove_counts(number);
+
+        Ok(())
+    }
+
+    fn remove_counts(&mut self, number: &B) {
+        for (pos, one_count) in self.one_counts.iter_mut().enumerate() {
+            if number.bit(pos) {
+                *one_count -= 1;
+            }
+        }
+        self.len -= 1;
+    }
+
+    /// Compute the current gamma and epsilon rates.
+    pub fn rates(&self, policy: TiePolicy) -> Result<Rates<B>, TieError> {
+        compute_rates(&self.one_counts, self.len, policy)
+    }
+}
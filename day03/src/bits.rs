@@ -0,0 +1,172 @@
+use std::fmt;
+
+use anyhow::{bail, Error};
+
+/// A number represented as a sequence of binary digits.
+pub trait Bits: Clone {
+    /// A number with every digit set to zero.
+    fn zero(digits: usize) -> Self;
+
+    /// Check if the digit in the position `pos` is `1`.
+    fn bit(&self, pos: usize) -> bool;
+
+    /// Set the digit in the position `pos` to `1`.
+    fn set_bit(&mut self, pos: usize);
+}
+
+impl Bits for u64 {
+    fn zero(_digits: usize) -> Self {
+        0
+    }
+
+    fn bit(&self, pos: usize) -> bool {
+        (self >> pos) & 1 == 1
+    }
+
+    fn set_bit(&mut self, pos: usize) {
+        *self |= 1 << pos;
+    }
+}
+
+/// A number with an arbitrary amount of binary digits.
+#[derive(Debug, Clone)]
+pub struct BitVec {
+    /// The digits of the number in groups of 64, starting with the least significant group.
+    words: Vec<u64>,
+}
+
+impl BitVec {
+    /// Parse a string of binary digits.
+    pub fn from_binary(s: &str) -> Result<Self, Error> {
+        if s.is_empty() || !s.bytes().all(|b| b == b'0' || b == b'1') {
+            bail!("invalid binary number {:?}", s);
+        }
+
+        // Parse groups of 64 digits starting from the end of the string, which has the least
+        // significant digits. All the digits are ASCII so the string can be split anywhere.
+        let words = s
+            .as_bytes()
+            .rchunks(64)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0, |word, digit| (word << 1) | u64::from(digit - b'0'))
+            })
+            .collect();
+
+        Ok(Self { words })
+    }
+
+    /// Build a number from its digits in base `radix`, starting from the most significant one.
+    pub fn from_digits(digits: &[u8], radix: u32) -> Self {
+        let mut words = vec![0u64];
+
+        // Multiply the number by `radix` and add each digit.
+        for &digit in digits {
+            let mut carry = u128::from(digit);
+            for word in words.iter_mut() {
+                let value = u128::from(*word) * u128::from(radix) + carry;
+                *word = value as u64;
+                carry = value >> 64;
+            }
+            if carry > 0 {
+                words.push(carry as u64);
+            }
+        }
+
+        Self { words }
+    }
+
+    /// Multiply two numbers.
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut words = vec![0u64; self.words.len() + other.words.len()];
+
+        // Schoolbook multiplication using 128-bit integers to keep the carry.
+        for (i, &a) in self.words.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b) in other.words.iter().enumerate() {
+                let product = u128::from(a) * u128::from(b) + u128::from(words[i + j]) + carry;
+                words[i + j] = product as u64;
+                carry = product >> 64;
+            }
+            words[i + other.words.len()] = carry as u64;
+        }
+
+        Self { words }
+    }
+}
+
+/// Two numbers are equal if they have the same value, regardless of their amount of digits.
+impl PartialEq for BitVec {
+    fn eq(&self, other: &Self) -> bool {
+        let (short, long) = if self.words.len() <= other.words.len() {
+            (&self.words, &other.words)
+        } else {
+            (&other.words, &self.words)
+        };
+
+        long[..short.len()] == short[..] && long[short.len()..].iter().all(|&word| word == 0)
+    }
+}
+
+impl Eq for BitVec {}
+
+impl From<u64> for BitVec {
+    fn from(value: u64) -> Self {
+        Self { words: vec![value] }
+    }
+}
+
+impl Bits for BitVec {
+    fn zero(digits: usize) -> Self {
+        Self {
+            words: vec![0; digits.div_ceil(64).max(1)],
+        }
+    }
+
+    fn bit(&self, pos: usize) -> bool {
+        self.words
+            .get(pos / 64)
+            .is_some_and(|word| word.bit(pos % 64))
+    }
+
+    fn set_bit(&mut self, pos: usize) {
+        if self.words.len() <= pos / 64 {
+            self.words.resize(pos / 64 + 1, 0);
+        }
+        self.words[pos / 64].set_bit(pos % 64);
+    }
+}
+
+/// Numbers are displayed in decimal.
+impl fmt::Display for BitVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The largest power of ten that fits in a `u64`.
+        const BASE: u64 = 10_000_000_000_000_000_000;
+
+        let mut words = self.words.clone();
+        let mut chunks = Vec::new();
+
+        // Repeatedly divide by `BASE` to get the decimal digits in groups of 19.
+        while words.iter().any(|&word| word != 0) {
+            let mut remainder = 0u128;
+            for word in words.iter_mut().rev() {
+                let value = (remainder << 64) | u128::from(*word);
+                *word = (value / u128::from(BASE)) as u64;
+                remainder = value % u128::from(BASE);
+            }
+            chunks.push(remainder as u64);
+        }
+
+        match chunks.split_last() {
+            None => f.pad("0"),
+            Some((first, rest)) => {
+                let mut s = first.to_string();
+                for chunk in rest.iter().rev() {
+                    s.push_str(&format!("{:019}", chunk));
+                }
+                f.pad(&s)
+            }
+        }
+    }
+}
@@ -1,7 +1,21 @@
-use anyhow::Error;
+use std::{fmt, num::NonZeroUsize, time::Instant};
+
+use aggregate::Aggregator;
+use anyhow::{bail, Context, Error};
+use bits::{BitVec, Bits};
+use popcount::{count_ones_parallel, count_ones_sliced};
+use trace::{RateError, Trace};
+use trie::Trie;
+
+mod aggregate;
+mod bits;
+mod popcount;
+mod radix;
+mod trace;
+mod trie;
 
 /// Counts the number of one-digits in `numbers` for each position.
-fn count_ones(numbers: &[u64], digits: usize) -> Vec<usize> {
+fn count_ones<B: Bits>(numbers: &[B], digits: usize) -> Vec<usize> {
     // This array will contain the number of ocurrences of the digit one in each position.
     let mut one_counts = vec![0; digits];
 
@@ -10,7 +24,7 @@ fn count_ones(numbers: &[u64], digits: usize) -> Vec<usize> {
         // Iterate over each position and update the number of one-ocurrences if required.
         for (pos, one_count) in one_counts.iter_mut().enumerate() {
             // If the digit in the position `pos` is `1`, increase the count for that position.
-            if (number >> pos) & 1 == 1 {
+            if number.bit(pos) {
                 *one_count += 1;
             }
         }
@@ -19,101 +33,500 @@ fn count_ones(numbers: &[u64], digits: usize) -> Vec<usize> {
     one_counts
 }
 
+/// How to pick the digits of the rates in a position where there are as many ones as zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TiePolicy {
+    /// Both the most and least common digits are `1`, or the largest tied digits in other bases.
+    PreferOne,
+    /// Both the most and least common digits are `0`, or the smallest tied digits in other bases.
+    PreferZero,
+    /// Ties are reported as an error.
+    Error,
+    /// The most common digit is `1` and the least common digit is `0`, as in the bit criteria of
+    /// the second part.
+    Exact,
+}
+
+impl std::str::FromStr for TiePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefer-one" | "prefer-high" => Ok(Self::PreferOne),
+            "prefer-zero" | "prefer-low" => Ok(Self::PreferZero),
+            "error" => Ok(Self::Error),
+            "exact" => Ok(Self::Exact),
+            _ => bail!("invalid tie policy {:?}", s),
+        }
+    }
+}
+
+/// Error returned when a column is tied and the policy is `TiePolicy::Error`.
+#[derive(Debug, PartialEq, Eq)]
+struct TieError {
+    /// The tied column, counting from the leftmost digit.
+    column: usize,
+}
+
+impl fmt::Display for TieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {} has as many ones as zeros", self.column)
+    }
+}
+
+impl std::error::Error for TieError {}
+
+/// The gamma and epsilon rates and the columns that needed special handling to compute them.
+#[derive(Debug, PartialEq, Eq)]
+struct Rates<B> {
+    gamma: B,
+    epsilon: B,
+    /// The columns with as many ones as zeros, counting from the leftmost digit.
+    tied: Vec<usize>,
+    /// The columns where every number has the same digit, counting from the leftmost digit.
+    constant: Vec<usize>,
+}
+
 /// Compute the gamma and epsilon rate from the count of one-digits and the total amount of
 /// numbers.
-fn compute_rates(one_counts: &[usize], numbers_len: usize) -> (u64, u64) {
-    // The gamma rate is composed by the most common digits of each position. All digits are set to
-    // zero.
-    let mut gamma_rate: u64 = 0;
+fn compute_rates<B: Bits>(
+    one_counts: &[usize],
+    numbers_len: usize,
+    policy: TiePolicy,
+) -> Result<Rates<B>, TieError> {
+    // The gamma rate is composed by the most common digits of each position and the epsilon rate
+    // by the least common ones. All digits are set to zero.
+    let mut rates = Rates {
+        gamma: B::zero(one_counts.len()),
+        epsilon: B::zero(one_counts.len()),
+        tied: Vec::new(),
+        constant: Vec::new(),
+    };
+
+    // Iterate over each position, starting from the leftmost one, and get the number of
+    // one-ocurrences.
+    for (pos, &one_count) in one_counts.iter().enumerate().rev() {
+        let column = one_counts.len() - 1 - pos;
+        let zero_count = numbers_len - one_count;
 
-    // Iterate over each position and get the number of one-ocurrences.
-    for (pos, one_count) in one_counts.iter().enumerate() {
-        // If more than half of the numbers had a `1` in the current position, the digit of the
-        // gamma rate in this position is `1`. Otherwise we left it be zero.
-        if 2 * one_count >= numbers_len {
-            gamma_rate |= 1 << pos;
+        let (most_common, least_common) = if one_count == 0 || zero_count == 0 {
+            // If the position only had one of the two possible digits, that digit is both the
+            // most and the least common one. Taking the complement of the gamma rate would
+            // use a digit that does not appear at all.
+            rates.constant.push(column);
+            (one_count > 0, one_count > 0)
+        } else if one_count == zero_count {
+            rates.tied.push(column);
+            match policy {
+                TiePolicy::PreferOne => (true, true),
+                TiePolicy::PreferZero => (false, false),
+                TiePolicy::Error => return Err(TieError { column }),
+                TiePolicy::Exact => (true, false),
+            }
+        } else {
+            (one_count > zero_count, one_count < zero_count)
+        };
+
+        if most_common {
+            rates.gamma.set_bit(pos);
+        }
+        if least_common {
+            rates.epsilon.set_bit(pos);
         }
     }
 
-    // The epsilon rate is composed by the least common digits of each position. This means that
-    // this is just the complement of the gamma rate but we have to trim any extra left-bits to
-    // have the right number of digits.
-    //
-    // This complement trick might fail if one of the positions only had one of the two
-    // possible digits.
-    let epsilon_rate = (!gamma_rate) & ((1 << (one_counts.len() as u32)) - 1);
+    Ok(rates)
+}
 
-    (gamma_rate, epsilon_rate)
+/// How to filter the numbers to compute the oxygen and CO2 ratings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    /// Build a trie once and walk it for each rating.
+    Trie,
+    /// Filter a copy of the numbers for each rating.
+    Retain,
 }
 
-fn compute_rate(
-    mut numbers: Vec<u64>,
+impl std::str::FromStr for Engine {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trie" => Ok(Self::Trie),
+            "retain" => Ok(Self::Retain),
+            _ => bail!("invalid engine {:?}", s),
+        }
+    }
+}
+
+/// How to print the trace of the oxygen and CO2 ratings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TraceFormat {
+    Table,
+    Json,
+}
+
+/// Find the only number left after keeping the numbers with the digit picked by `pick_bit` in
+/// each position, starting from the most significant one.
+///
+/// Each position is recorded in `trace` if it is not `None`.
+fn compute_rate<B: Bits>(
+    numbers: Vec<B>,
     digits: usize,
     pick_bit: fn(usize, usize) -> bool,
-) -> Option<u64> {
-    let mut rate = None;
+    trace: Option<&mut Trace>,
+) -> Result<B, RateError> {
+    compute_rate_by(
+        numbers,
+        digits,
+        2,
+        |number, column| u8::from(number.bit(digits - 1 - column)),
+        |counts| u8::from(pick_bit(counts[1], counts[0] + counts[1])),
+        trace,
+    )
+}
 
-    for position in (0..digits).rev() {
-        let ones_count = numbers
-            .iter()
-            .filter(|&number| (number >> position) & 1 == 1)
-            .count();
+/// Same as `compute_rate` but for numbers in base `radix`, whose digit in each column, counting
+/// from the leftmost one, is returned by `digit`.
+///
+/// `pick_digit` receives the amount of remaining numbers with each digit in the current column
+/// and returns the digit the numbers must have in that column to be kept. The trace only records
+/// the amount of ones, so it is meant for binary numbers.
+fn compute_rate_by<N: Clone>(
+    mut numbers: Vec<N>,
+    digits: usize,
+    radix: u32,
+    digit: impl Fn(&N, usize) -> u8,
+    pick_digit: impl Fn(&[usize]) -> u8,
+    mut trace: Option<&mut Trace>,
+) -> Result<N, RateError> {
+    if numbers.is_empty() {
+        return Err(RateError::Empty);
+    }
+
+    for column in 0..digits {
+        let mut counts = vec![0; radix as usize];
+        for number in &numbers {
+            counts[digit(number, column) as usize] += 1;
+        }
+
+        let picked_digit = pick_digit(&counts);
+
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.record(column, counts[1], numbers.len(), picked_digit == 1);
+        }
+
+        numbers.retain(|number| digit(number, column) == picked_digit);
+
+        match numbers.len() {
+            0 => {
+                return Err(RateError::NoneLeft {
+                    column,
+                    picked_digit,
+                })
+            }
+            1 => return Ok(numbers.swap_remove(0)),
+            _ => {}
+        }
+    }
+
+    Err(RateError::Duplicates {
+        remaining: numbers.len(),
+    })
+}
+
+/// Compute and print the answers for both parts.
+///
+/// The products are computed with `BitVec` because they have twice as many digits as the rates.
+/// The number of one-digits in each position must be already counted in `one_counts`.
+fn print_answers<B: Bits + Into<BitVec>>(
+    numbers: Vec<B>,
+    digits: usize,
+    one_counts: &[usize],
+    policy: TiePolicy,
+    engine: Engine,
+    trace_format: Option<TraceFormat>,
+) -> Result<(), Error> {
+    // Compute the rates.
+    let rates: Rates<B> = compute_rates(one_counts, numbers.len(), policy)?;
+
+    println!("Part 1: {}", rates.gamma.into().mul(&rates.epsilon.into()));
+    if !rates.tied.is_empty() {
+        eprintln!("Tied columns: {:?}", rates.tied);
+    }
+    if !rates.constant.is_empty() {
+        eprintln!("Constant columns: {:?}", rates.constant);
+    }
+
+    let most_common = |ones_count, len| 2 * ones_count >= len;
+    let least_common = |ones_count, len| 2 * ones_count < len;
+
+    let mut oxygen_trace = Trace::default();
+    let mut co2_trace = Trace::default();
+
+    let (oxygen_rate, co2_rate): (Result<B, _>, Result<B, _>) = match engine {
+        Engine::Trie => {
+            let trie = Trie::new(&numbers, digits);
+            (
+                trie.filter(most_common, Some(&mut oxygen_trace)),
+                trie.filter(least_common, Some(&mut co2_trace)),
+            )
+        }
+        Engine::Retain => (
+            compute_rate(
+                numbers.clone(),
+                digits,
+                most_common,
+                Some(&mut oxygen_trace),
+            ),
+            compute_rate(numbers, digits, least_common, Some(&mut co2_trace)),
+        ),
+    };
+
+    // Print the traces before checking the ratings so they can explain the errors.
+    match trace_format {
+        Some(TraceFormat::Table) => {
+            println!("Oxygen generator rating:\n{}", oxygen_trace);
+            println!("CO2 scrubber rating:\n{}", co2_trace);
+        }
+        Some(TraceFormat::Json) => println!(
+            "{{\"oxygen\":{},\"co2\":{}}}",
+            oxygen_trace.to_json(),
+            co2_trace.to_json()
+        ),
+        None => {}
+    }
+
+    let oxygen_rate = oxygen_rate.context("cannot compute the oxygen generator rating")?;
+    let co2_rate = co2_rate.context("cannot compute the CO2 scrubber rating")?;
+
+    println!("Part 2: {}", oxygen_rate.into().mul(&co2_rate.into()));
+
+    Ok(())
+}
+
+/// Same as `print_answers` but for numbers in base `radix`.
+fn print_digit_answers(
+    numbers: Vec<Vec<u8>>,
+    digits: usize,
+    radix: u32,
+    policy: TiePolicy,
+) -> Result<(), Error> {
+    let digit_counts = radix::count_digits(&numbers, digits, radix);
+    let rates = radix::compute_digit_rates(&digit_counts, policy)?;
+
+    let gamma_rate = BitVec::from_digits(&rates.gamma, radix);
+    let epsilon_rate = BitVec::from_digits(&rates.epsilon, radix);
+    println!("Part 1: {}", gamma_rate.mul(&epsilon_rate));
+    if !rates.tied.is_empty() {
+        eprintln!("Tied columns: {:?}", rates.tied);
+    }
+    if !rates.constant.is_empty() {
+        eprintln!("Constant columns: {:?}", rates.constant);
+    }
+
+    let oxygen_rate = radix::compute_digit_rate(numbers.clone(), digits, radix, radix::most_common)
+        .context("cannot compute the oxygen generator rating")?;
+    let co2_rate = radix::compute_digit_rate(numbers, digits, radix, radix::least_common)
+        .context("cannot compute the CO2 scrubber rating")?;
+
+    let oxygen_rate = BitVec::from_digits(&oxygen_rate, radix);
+    let co2_rate = BitVec::from_digits(&co2_rate, radix);
+    println!("Part 2: {}", oxygen_rate.mul(&co2_rate));
 
-        let picked_bit = pick_bit(ones_count, numbers.len()) as u64;
+    Ok(())
+}
 
-        numbers.retain(|&number| (number >> position) & 1 == picked_bit);
+/// Read numbers from stdin and print the rates and their product after each one.
+///
+/// Lines starting with `-` retract the number instead of adding it. Only the last `window`
+/// numbers are kept if `window` is not `None`.
+fn stream(policy: TiePolicy, window: Option<NonZeroUsize>) -> Result<(), Error> {
+    let mut aggregator = None;
+
+    for line in std::io::stdin().lines() {
+        let line = line?;
+        let (retract, digits) = match line.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, line.as_str()),
+        };
+        if digits.len() > 64 {
+            bail!(
+                "{:?} has more than 64 digits, which cannot be streamed",
+                digits
+            );
+        }
+
+        // The first number sets the number of digits.
+        let aggregator = aggregator.get_or_insert_with(|| match window {
+            Some(window) => Aggregator::with_window(digits.len(), window),
+            None => Aggregator::new(digits.len()),
+        });
+
+        if digits.len() != aggregator.one_counts().len() {
+            bail!(
+                "{:?} does not have {} digits",
+                digits,
+                aggregator.one_counts().len()
+            );
+        }
+        let number = u64::from_str_radix(digits, 2)?;
 
-        if numbers.len() == 1 {
-            rate = Some(numbers[0]);
-            break;
+        if retract {
+            aggregator.retract(&number)?;
+        } else {
+            aggregator.push(number);
         }
+
+        let rates = aggregator.rates(policy)?;
+        println!(
+            "{} numbers: gamma {} epsilon {} power {}",
+            aggregator.len(),
+            rates.gamma,
+            rates.epsilon,
+            BitVec::from(rates.gamma).mul(&rates.epsilon.into())
+        );
     }
 
-    rate
+    Ok(())
+}
+
+/// Time `count` on `numbers` and print the average duration.
+fn bench(
+    name: &str,
+    numbers: &[u64],
+    digits: usize,
+    count: impl Fn(&[u64], usize) -> Vec<usize>,
+) -> Vec<usize> {
+    const ITERATIONS: u32 = 100;
+
+    let start = Instant::now();
+    let mut one_counts = Vec::new();
+    for _ in 0..ITERATIONS {
+        one_counts = count(std::hint::black_box(numbers), digits);
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+
+    println!("{:<10} {:>12?}", name, elapsed);
+
+    one_counts
 }
 
 fn main() -> Result<(), Error> {
+    let mut policy = TiePolicy::Exact;
+    let mut engine = None;
+    let mut trace_format = None;
+    let mut threads = None;
+    let mut run_bench = false;
+    let mut radix = 2;
+    let mut from_stdin = false;
+    let mut window = None;
+
+    // `--threads=<n>` counts the one-digits with `n` threads and `--bench` compares the ways of
+    // counting them instead of printing the answers. `--radix=<n>` reads the numbers in base `n`,
+    // which cannot be combined with the flags that only apply to binary numbers. `-` reads the
+    // numbers from stdin and prints the rates after each one, using only the last `k` numbers if
+    // `--window=<k>` is passed.
+    for arg in std::env::args().skip(1) {
+        if let Some(value) = arg.strip_prefix("--ties=") {
+            policy = value.parse()?;
+        } else if let Some(value) = arg.strip_prefix("--engine=") {
+            engine = Some(value.parse()?);
+        } else if arg == "--trace" || arg == "--trace=table" {
+            trace_format = Some(TraceFormat::Table);
+        } else if arg == "--trace=json" {
+            trace_format = Some(TraceFormat::Json);
+        } else if let Some(value) = arg.strip_prefix("--threads=") {
+            threads = Some(value.parse()?);
+        } else if arg == "--bench" {
+            run_bench = true;
+        } else if arg == "-" {
+            from_stdin = true;
+        } else if let Some(value) = arg.strip_prefix("--window=") {
+            window = Some(value.parse()?);
+        } else if let Some(value) = arg.strip_prefix("--radix=") {
+            radix = value.parse()?;
+            if !(2..=36).contains(&radix) {
+                bail!("the radix must be between 2 and 36, got {}", radix);
+            }
+        } else {
+            bail!("unexpected argument {:?}", arg);
+        }
+    }
+
+    if radix != 2 && (engine.is_some() || trace_format.is_some() || threads.is_some()) {
+        bail!("--engine, --trace and --threads only work with binary numbers");
+    }
+    let engine = engine.unwrap_or(Engine::Trie);
+    let threads = threads.unwrap_or(NonZeroUsize::MIN);
+
+    if from_stdin {
+        if radix != 2 {
+            bail!("only binary numbers can be streamed");
+        }
+        return stream(policy, window);
+    }
+
     let input = std::fs::read_to_string("./input")?;
     // Iterator over the lines of the input file.
     let mut lines = input.lines();
-    // We will store the input parsed as integers here.
-    let mut numbers = Vec::with_capacity(lines.size_hint().0);
 
     // We need to special-case the first line to extract the number of digits.
     if let Some(first_line) = lines.next() {
         // The number of digits is just the length of the first line.
         let digits = first_line.len();
 
-        // Panic if the input numbers are too long to fit into `u64` integers.
-        assert!(
-            digits <= u64::BITS.try_into().unwrap(),
-            "numbers are too long"
-        );
+        // Use `u64` integers if the input numbers are binary and short enough because they are
+        // faster. Otherwise parse them as bit vectors or as lists of digits.
+        if radix != 2 {
+            let numbers = input
+                .lines()
+                .map(|line| {
+                    if line.len() != digits {
+                        bail!("{:?} does not have {} digits", line, digits);
+                    }
+                    radix::parse_digits(line, radix)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
 
-        // Parse the first line and push it to the numbers buffer if successful.
-        numbers.push(u64::from_str_radix(first_line, 2)?);
-        // Parse the remaining lines and push it to the numbers buffer if successful.
-        for line in lines {
-            numbers.push(u64::from_str_radix(line, 2)?);
-        }
-        // Count the number of one-digits in each position.
-        let one_counts = count_ones(&numbers, digits);
-        // Compute the rates.
-        let (gamma_rate, epsilon_rate) = compute_rates(&one_counts, numbers.len());
+            if run_bench {
+                bail!("the benchmark needs binary numbers");
+            }
 
-        println!("Part 1: {}", gamma_rate * epsilon_rate);
+            print_digit_answers(numbers, digits, radix, policy)?;
+        } else if digits <= u64::BITS as usize {
+            let numbers = input
+                .lines()
+                .map(|line| u64::from_str_radix(line, 2))
+                .collect::<Result<Vec<_>, _>>()?;
 
-        let oxygen_rate = compute_rate(numbers.clone(), digits, |ones_count, len| {
-            2 * ones_count >= len
-        })
-        .unwrap();
+            if run_bench {
+                let threads = std::thread::available_parallelism()?;
+                let expected = bench("loop", &numbers, digits, count_ones);
+                let sliced = bench("sliced", &numbers, digits, count_ones_sliced);
+                let parallel = bench("parallel", &numbers, digits, |numbers, digits| {
+                    count_ones_parallel(numbers, digits, threads)
+                });
+                assert_eq!(expected, sliced);
+                assert_eq!(expected, parallel);
+                return Ok(());
+            }
 
-        let co2_rate = compute_rate(numbers.clone(), digits, |ones_count, len| {
-            2 * ones_count < len
-        })
-        .unwrap();
+            let one_counts = count_ones_parallel(&numbers, digits, threads);
+            print_answers(numbers, digits, &one_counts, policy, engine, trace_format)?;
+        } else {
+            let numbers = input
+                .lines()
+                .map(BitVec::from_binary)
+                .collect::<Result<Vec<_>, _>>()?;
 
-        println!("Part 2: {}", oxygen_rate * co2_rate);
+            if run_bench {
+                bail!("the benchmark needs numbers with at most 64 digits");
+            }
+
+            let one_counts = count_ones(&numbers, digits);
+            print_answers(numbers, digits, &one_counts, policy, engine, trace_format)?;
+        }
     } else {
         eprintln!("Input is empty");
     }
@@ -133,10 +546,74 @@ mod tests {
         ];
 
         let one_counts = count_ones(&input, 5);
-        let (gamma_rate, epsilon_rate) = compute_rates(&one_counts, input.len());
+        let rates = compute_rates::<u64>(&one_counts, input.len(), TiePolicy::Exact).unwrap();
+
+        assert_eq!(0b10110, rates.gamma);
+        assert_eq!(0b01001, rates.epsilon);
+    }
+
+    #[test]
+    fn wide() {
+        let input = [
+            "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
+            "11001", "00010", "01010",
+        ];
+        // Repeat every number 20 times so they are 100 digits long.
+        let wide = |line: &str| BitVec::from_binary(&line.repeat(20)).unwrap();
+        let numbers = input.iter().map(|line| wide(line)).collect::<Vec<_>>();
 
-        assert_eq!(0b10110, gamma_rate);
-        assert_eq!(0b01001, epsilon_rate);
+        let one_counts = count_ones(&numbers, 100);
+        let narrow = input.map(|line| u64::from_str_radix(line, 2).unwrap());
+        assert_eq!(count_ones(&narrow, 5).repeat(20), one_counts);
+
+        let rates = compute_rates::<BitVec>(&one_counts, numbers.len(), TiePolicy::Exact).unwrap();
+        assert_eq!(wide("10110"), rates.gamma);
+        assert_eq!(wide("01001"), rates.epsilon);
+
+        let most_common = |ones_count: usize, len| 2 * ones_count >= len;
+        let least_common = |ones_count: usize, len| 2 * ones_count < len;
+        assert_eq!(
+            Ok(wide("10111")),
+            compute_rate(numbers.clone(), 100, most_common, None)
+        );
+        assert_eq!(
+            Ok(wide("01010")),
+            compute_rate(numbers.clone(), 100, least_common, None)
+        );
+
+        let trie = Trie::new(&numbers, 100);
+        assert_eq!(Ok(wide("10111")), trie.filter(most_common, None));
+        assert_eq!(Ok(wide("01010")), trie.filter(least_common, None));
+
+        assert!(BitVec::from_binary("10201").is_err());
+        let max = BitVec::from(u64::MAX);
+        assert_eq!(
+            "340282366920938463426481119284349108225",
+            max.mul(&max).to_string()
+        );
+    }
+
+    #[test]
+    fn ties() {
+        // The first column is constant and the last two are tied.
+        let input = [0b101, 0b110, 0b111, 0b100];
+        let one_counts = count_ones(&input, 3);
+
+        let rates = compute_rates::<u64>(&one_counts, input.len(), TiePolicy::Exact).unwrap();
+        assert_eq!((0b111, 0b100), (rates.gamma, rates.epsilon));
+        assert_eq!(vec![1, 2], rates.tied);
+        assert_eq!(vec![0], rates.constant);
+
+        let rates = compute_rates::<u64>(&one_counts, input.len(), TiePolicy::PreferOne).unwrap();
+        assert_eq!((0b111, 0b111), (rates.gamma, rates.epsilon));
+
+        let rates = compute_rates::<u64>(&one_counts, input.len(), TiePolicy::PreferZero).unwrap();
+        assert_eq!((0b100, 0b100), (rates.gamma, rates.epsilon));
+
+        assert_eq!(
+            Err(TieError { column: 1 }),
+            compute_rates::<u64>(&one_counts, input.len(), TiePolicy::Error)
+        );
     }
 
     #[test]
@@ -147,12 +624,249 @@ mod tests {
         ];
 
         assert_eq!(
-            Some(0b10111),
-            compute_rate(input.to_vec(), 5, |ones_count, len| 2 * ones_count >= len)
+            Ok(0b10111),
+            compute_rate(
+                input.to_vec(),
+                5,
+                |ones_count, len| 2 * ones_count >= len,
+                None
+            )
+        );
+        assert_eq!(
+            Ok(0b01010),
+            compute_rate(
+                input.to_vec(),
+                5,
+                |ones_count, len| 2 * ones_count < len,
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn sliced() {
+        let input = [
+            0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
+            0b11001, 0b00010, 0b01010,
+        ];
+        assert_eq!(count_ones(&input, 5), count_ones_sliced(&input, 5));
+
+        // Use enough numbers to fill several blocks with every digit.
+        let numbers = (0..1000u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect::<Vec<_>>();
+        let one_counts = count_ones(&numbers, 64);
+        assert_eq!(one_counts, count_ones_sliced(&numbers, 64));
+        for threads in [1, 3, 8] {
+            let threads = NonZeroUsize::new(threads).unwrap();
+            assert_eq!(one_counts, count_ones_parallel(&numbers, 64, threads));
+        }
+    }
+
+    #[test]
+    fn radix() {
+        let input = [
+            "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
+            "11001", "00010", "01010",
+        ];
+
+        // The binary numbers give the same answers with digits.
+        let numbers = input
+            .iter()
+            .map(|line| radix::parse_digits(line, 2).unwrap())
+            .collect::<Vec<_>>();
+        let digit_counts = radix::count_digits(&numbers, 5, 2);
+        let rates = radix::compute_digit_rates(&digit_counts, TiePolicy::Exact).unwrap();
+        assert_eq!(BitVec::from(0b10110), BitVec::from_digits(&rates.gamma, 2));
+        assert_eq!(
+            BitVec::from(0b01001),
+            BitVec::from_digits(&rates.epsilon, 2)
+        );
+        assert_eq!(
+            Ok(vec![1, 0, 1, 1, 1]),
+            radix::compute_digit_rate(numbers.clone(), 5, 2, radix::most_common)
+        );
+        assert_eq!(
+            Ok(vec![0, 1, 0, 1, 0]),
+            radix::compute_digit_rate(numbers, 5, 2, radix::least_common)
+        );
+
+        // The first column is constant, the second one has three digits and the third one is
+        // tied between `a` and `f`.
+        let input = ["1af", "1aa", "12f", "1fa"];
+        let numbers = input
+            .iter()
+            .map(|line| radix::parse_digits(line, 16).unwrap())
+            .collect::<Vec<_>>();
+        let digit_counts = radix::count_digits(&numbers, 3, 16);
+
+        let rates = radix::compute_digit_rates(&digit_counts, TiePolicy::Exact).unwrap();
+        assert_eq!(vec![1, 10, 15], rates.gamma);
+        assert_eq!(vec![1, 2, 10], rates.epsilon);
+        assert_eq!(vec![1, 2], rates.tied);
+        assert_eq!(vec![0], rates.constant);
+
+        let rates = radix::compute_digit_rates(&digit_counts, TiePolicy::PreferOne).unwrap();
+        assert_eq!(
+            (vec![1, 10, 15], vec![1, 15, 15]),
+            (rates.gamma, rates.epsilon)
+        );
+        let rates = radix::compute_digit_rates(&digit_counts, TiePolicy::PreferZero).unwrap();
+        assert_eq!(
+            (vec![1, 10, 10], vec![1, 2, 10]),
+            (rates.gamma, rates.epsilon)
+        );
+        assert_eq!(
+            Err(TieError { column: 1 }),
+            radix::compute_digit_rates(&digit_counts, TiePolicy::Error)
+        );
+
+        assert_eq!(
+            Ok(vec![1, 10, 15]),
+            radix::compute_digit_rate(numbers.clone(), 3, 16, radix::most_common)
+        );
+        assert_eq!(
+            Ok(vec![1, 2, 15]),
+            radix::compute_digit_rate(numbers, 3, 16, radix::least_common)
+        );
+        assert_eq!(BitVec::from(0x1af), BitVec::from_digits(&[1, 10, 15], 16));
+        assert_eq!(
+            Err(RateError::Empty),
+            radix::compute_digit_rate(Vec::new(), 3, 16, radix::most_common)
+        );
+
+        assert!(radix::parse_digits("acgt", 4).is_err());
+    }
+
+    #[test]
+    fn aggregate() {
+        let input = [
+            0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
+            0b11001, 0b00010, 0b01010,
+        ];
+
+        let mut aggregator = Aggregator::new(5);
+        for number in input {
+            aggregator.push(number);
+        }
+        assert_eq!(count_ones(&input, 5), aggregator.one_counts());
+        let rates = aggregator.rates(TiePolicy::Exact).unwrap();
+        assert_eq!((0b10110, 0b01001), (rates.gamma, rates.epsilon));
+
+        // Retracting the last numbers is the same as never adding them.
+        aggregator.retract(&0b01010).unwrap();
+        aggregator.retract(&0b00010).unwrap();
+        assert_eq!(count_ones(&input[..10], 5), aggregator.one_counts());
+        assert_eq!(10, aggregator.len());
+
+        let mut aggregator = Aggregator::new(5);
+        aggregator.push(0b00100);
+        assert_eq!(Err(aggregate::RetractError), aggregator.retract(&0b00010));
+
+        // Every number added has a one in each position, so none of them can be 000.
+        let mut aggregator = Aggregator::new(3);
+        aggregator.push(0b111);
+        assert_eq!(Err(aggregate::RetractError), aggregator.retract(&0b000));
+
+        // The window only keeps the last 4 numbers.
+        let window = NonZeroUsize::new(4).unwrap();
+        let mut aggregator = Aggregator::with_window(5, window);
+        for (i, &number) in input.iter().enumerate() {
+            aggregator.push(number);
+            let start = (i + 1).saturating_sub(4);
+            assert_eq!(count_ones(&input[start..=i], 5), aggregator.one_counts());
+        }
+        assert_eq!(Err(aggregate::RetractError), aggregator.retract(&0b00100));
+        aggregator.retract(&0b00010).unwrap();
+        assert_eq!(
+            count_ones(&[0b10000, 0b11001, 0b01010], 5),
+            aggregator.one_counts()
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let input = [
+            0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
+            0b11001, 0b00010, 0b01010,
+        ];
+        let least_common = |ones_count: usize, len| 2 * ones_count < len;
+
+        let mut trace = Trace::default();
+        compute_rate(input.to_vec(), 5, least_common, Some(&mut trace)).unwrap();
+        assert_eq!(
+            vec![(12, 7, false, 7), (5, 2, true, 3), (2, 1, false, 1)],
+            trace
+                .steps
+                .iter()
+                .map(|step| (
+                    step.remaining,
+                    step.ones_count,
+                    step.picked_bit,
+                    step.eliminated
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert!(trace.to_json().starts_with(
+            "[{\"column\":0,\"ones_count\":7,\"remaining\":12,\"picked_bit\":0,\"eliminated\":7},"
+        ));
+
+        // The trie takes the same steps.
+        let mut trie_trace = Trace::default();
+        Trie::new(&input, 5)
+            .filter::<u64>(least_common, Some(&mut trie_trace))
+            .unwrap();
+        assert_eq!(trace, trie_trace);
+
+        // No number has a `1` in the middle position.
+        let input = [0b100, 0b101];
+        let error = RateError::NoneLeft {
+            column: 1,
+            picked_digit: 1,
+        };
+        assert_eq!(
+            Err(error),
+            compute_rate(input.to_vec(), 3, |_, _| true, None)
+        );
+        assert_eq!(
+            Err(error),
+            Trie::new(&input, 3).filter::<u64>(|_, _| true, None)
+        );
+    }
+
+    #[test]
+    fn trie() {
+        let input = [
+            0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
+            0b11001, 0b00010, 0b01010,
+        ];
+        let trie = Trie::new(&input, 5);
+
+        assert_eq!(
+            Ok(0b10111),
+            trie.filter(|ones_count, len| 2 * ones_count >= len, None)
+        );
+        assert_eq!(
+            Ok(0b01010),
+            trie.filter(|ones_count, len| 2 * ones_count < len, None)
+        );
+        // Keep the numbers with a `0` until only one is left, which is the smallest.
+        assert_eq!(Ok(0b00010), trie.filter(|_, _| false, None));
+        // Keep the numbers with a `1` whenever there is one, which gives the largest.
+        assert_eq!(
+            Ok(0b11110),
+            trie.filter(|ones_count, _| ones_count > 0, None)
+        );
+
+        // Duplicated numbers cannot be filtered out.
+        let trie = Trie::new(&[0b101, 0b101, 0b011], 3);
+        assert_eq!(
+            Err(RateError::Duplicates { remaining: 2 }),
+            trie.filter::<u64>(|ones_count, len| 2 * ones_count >= len, None)
         );
         assert_eq!(
-            Some(0b01010),
-            compute_rate(input.to_vec(), 5, |ones_count, len| 2 * ones_count < len)
+            Ok(0b011),
+            trie.filter(|ones_count, len| 2 * ones_count < len, None)
         );
     }
 }
@@ -0,0 +1,71 @@
+use std::{num::NonZeroUsize, thread};
+
+/// Transpose a 64x64 bit matrix where each word is a row and the most significant bit is the
+/// first column.
+fn transpose(rows: &mut [u64; 64]) {
+    let mut width = 32;
+    let mut mask = 0x0000_0000_ffff_ffffu64;
+
+    // Swap the off-diagonal blocks of size `width` inside each block of size `2 * width`.
+    while width != 0 {
+        let mut k = 0;
+        while k < 64 {
+            let t = (rows[k] ^ (rows[k + width] >> width)) & mask;
+            rows[k] ^= t;
+            rows[k + width] ^= t << width;
+            k = (k + width + 1) & !width;
+        }
+        width >>= 1;
+        mask ^= mask << width;
+    }
+}
+
+/// Same as `count_ones` but the numbers are transposed in blocks of 64 so the digits in each
+/// position are counted for 64 numbers at the same time.
+///
+/// `digits` must be at most 64.
+pub fn count_ones_sliced(numbers: &[u64], digits: usize) -> Vec<usize> {
+    let mut one_counts = vec![0; digits];
+
+    for chunk in numbers.chunks(64) {
+        // Missing numbers in the last chunk are zero so they do not change the counts.
+        let mut block = [0u64; 64];
+        block[..chunk.len()].copy_from_slice(chunk);
+        transpose(&mut block);
+
+        // After transposing, the digits in the position `pos` of every number are in the word
+        // `63 - pos`.
+        for (pos, one_count) in one_counts.iter_mut().enumerate() {
+            *one_count += block[63 - pos].count_ones() as usize;
+        }
+    }
+
+    one_counts
+}
+
+/// Same as `count_ones_sliced` but the numbers are split between `threads` threads.
+pub fn count_ones_parallel(numbers: &[u64], digits: usize, threads: NonZeroUsize) -> Vec<usize> {
+    // Round up to a multiple of 64 so only the last chunk has an incomplete block.
+    let chunk_len = numbers
+        .len()
+        .div_ceil(threads.get())
+        .next_multiple_of(64)
+        .max(64);
+
+    thread::scope(|scope| {
+        let handles = numbers
+            .chunks(chunk_len)
+            .map(|chunk| scope.spawn(move || count_ones_sliced(chunk, digits)))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold(vec![0; digits], |mut total, counts| {
+                for (total, count) in total.iter_mut().zip(counts) {
+                    *total += count;
+                }
+                total
+            })
+    })
+}
@@ -0,0 +1,131 @@
+use anyhow::{bail, Error};
+
+use super::{compute_rate_by, RateError, Rates, TieError, TiePolicy};
+
+/// Parse the digits of a number in base `radix`, starting from the most significant one.
+pub fn parse_digits(line: &str, radix: u32) -> Result<Vec<u8>, Error> {
+    line.chars()
+        .map(|c| match c.to_digit(radix) {
+            Some(digit) => Ok(digit as u8),
+            None => bail!("invalid digit {:?} in {:?} for radix {}", c, line, radix),
+        })
+        .collect()
+}
+
+/// Count the ocurrences of each digit in each column of `numbers`.
+pub fn count_digits(numbers: &[Vec<u8>], digits: usize, radix: u32) -> Vec<Vec<usize>> {
+    let mut digit_counts = vec![vec![0; radix as usize]; digits];
+
+    for number in numbers {
+        for (counts, &digit) in digit_counts.iter_mut().zip(number) {
+            counts[digit as usize] += 1;
+        }
+    }
+
+    digit_counts
+}
+
+/// The most common digit in `counts`. Ties are broken towards the largest digit, like the bit
+/// criteria of the oxygen generator rating.
+pub fn most_common(counts: &[usize]) -> u8 {
+    (0..counts.len())
+        .max_by_key(|&digit| counts[digit])
+        .unwrap_or(0) as u8
+}
+
+/// The least common digit in `counts` that appears at least once. Ties are broken towards the
+/// smallest digit, like the bit criteria of the CO2 scrubber rating.
+pub fn least_common(counts: &[usize]) -> u8 {
+    (0..counts.len())
+        .filter(|&digit| counts[digit] > 0)
+        .min_by_key(|&digit| counts[digit])
+        .unwrap_or(0) as u8
+}
+
+/// Same as `compute_rates` but for numbers in any base.
+///
+/// The tie policies pick the largest tied digit instead of `1` and the smallest tied digit
+/// instead of `0`. The least common digit of a column is the least common one among the digits
+/// that appear in that column.
+pub fn compute_digit_rates(
+    digit_counts: &[Vec<usize>],
+    policy: TiePolicy,
+) -> Result<Rates<Vec<u8>>, TieError> {
+    let mut rates = Rates {
+        gamma: Vec::with_capacity(digit_counts.len()),
+        epsilon: Vec::with_capacity(digit_counts.len()),
+        tied: Vec::new(),
+        constant: Vec::new(),
+    };
+
+    for (column, counts) in digit_counts.iter().enumerate() {
+        let present = (0..counts.len() as u8)
+            .filter(|&digit| counts[digit as usize] > 0)
+            .collect::<Vec<_>>();
+
+        if present.len() <= 1 {
+            // The only digit that appears is both the most and the least common one.
+            let digit = present.first().copied().unwrap_or(0);
+            rates.constant.push(column);
+            rates.gamma.push(digit);
+            rates.epsilon.push(digit);
+            continue;
+        }
+
+        let count = |digit: &u8| counts[*digit as usize];
+        let max = present.iter().map(count).max().unwrap_or(0);
+        let min = present.iter().map(count).min().unwrap_or(0);
+        let most_tied = present
+            .iter()
+            .copied()
+            .filter(|digit| count(digit) == max)
+            .collect::<Vec<_>>();
+        let least_tied = present
+            .iter()
+            .copied()
+            .filter(|digit| count(digit) == min)
+            .collect::<Vec<_>>();
+
+        if most_tied.len() > 1 || least_tied.len() > 1 {
+            rates.tied.push(column);
+        }
+
+        // Both lists are sorted and not empty.
+        let (most_common, least_common) = match policy {
+            TiePolicy::PreferOne => (
+                most_tied[most_tied.len() - 1],
+                least_tied[least_tied.len() - 1],
+            ),
+            TiePolicy::PreferZero => (most_tied[0], least_tied[0]),
+            TiePolicy::Error if most_tied.len() > 1 || least_tied.len() > 1 => {
+                return Err(TieError { column })
+            }
+            TiePolicy::Error | TiePolicy::Exact => (most_tied[most_tied.len() - 1], least_tied[0]),
+        };
+
+        rates.gamma.push(most_common);
+        rates.epsilon.push(least_common);
+    }
+
+    Ok(rates)
+}
+
+/// Same as `compute_rate` but for numbers in any base.
+///
+/// `pick_digit` receives the amount of remaining numbers with each digit in the current column
+/// and returns the digit the numbers must have in that column to be kept.
+pub fn compute_digit_rate(
+    numbers: Vec<Vec<u8>>,
+    digits: usize,
+    radix: u32,
+    pick_digit: fn(&[usize]) -> u8,
+) -> Result<Vec<u8>, RateError> {
+    compute_rate_by(
+        numbers,
+        digits,
+        radix,
+        |number, column| number[column],
+        pick_digit,
+        None,
+    )
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+
+/// What happened when filtering the numbers by the digit in one column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// The column, counting from the leftmost digit.
+    pub column: usize,
+    /// The amount of remaining numbers with a `1` in this column.
+    pub ones_count: usize,
+    /// The amount of numbers remaining before filtering this column.
+    pub remaining: usize,
+    /// The digit the numbers must have in this column to be kept.
+    pub picked_bit: bool,
+    /// The amount of numbers that did not have the picked digit.
+    pub eliminated: usize,
+}
+
+/// The steps taken to compute a rating.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub steps: Vec<Step>,
+}
+
+impl Trace {
+    /// Record the step for a column.
+    pub fn record(&mut self, column: usize, ones_count: usize, remaining: usize, picked_bit: bool) {
+        let kept = if picked_bit {
+            ones_count
+        } else {
+            remaining - ones_count
+        };
+
+        self.steps.push(Step {
+            column,
+            ones_count,
+            remaining,
+            picked_bit,
+            eliminated: remaining - kept,
+        });
+    }
+
+    /// Render the trace as a JSON array with one object per step.
+    pub fn to_json(&self) -> String {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| {
+                format!(
+                    "{{\"column\":{},\"ones_count\":{},\"remaining\":{},\"picked_bit\":{},\"eliminated\":{}}}",
+                    step.column,
+                    step.ones_count,
+                    step.remaining,
+                    u8::from(step.picked_bit),
+                    step.eliminated
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!("[{}]", steps)
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>8}{:>12}{:>12}{:>8}{:>12}",
+            "column", "ones", "remaining", "bit", "eliminated"
+        )?;
+
+        for step in &self.steps {
+            writeln!(
+                f,
+                "{:>8}{:>12}{:>12}{:>8}{:>12}",
+                step.column,
+                step.ones_count,
+                step.remaining,
+                u8::from(step.picked_bit),
+                step.eliminated
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Error returned when filtering does not leave exactly one number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateError {
+    /// There were no numbers to filter.
+    Empty,
+    /// None of the remaining numbers had the picked digit in a column.
+    NoneLeft { column: usize, picked_digit: u8 },
+    /// More than one number was left after filtering every column, so they are all equal.
+    Duplicates { remaining: usize },
+}
+
+impl fmt::Display for RateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "there are no numbers to filter"),
+            Self::NoneLeft {
+                column,
+                picked_digit,
+            } => write!(
+                f,
+                "no numbers have a {} in column {}",
+                char::from_digit(u32::from(*picked_digit), 36).unwrap_or('?'),
+                column
+            ),
+            Self::Duplicates { remaining } => write!(
+                f,
+                "{} numbers are left after filtering every column",
+                remaining
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RateError {}
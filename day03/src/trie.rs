@@ -0,0 +1,119 @@
+use super::{Bits, RateError, Trace};
+
+/// A node of the trie.
+#[derive(Debug, Clone, Copy, Default)]
+struct Node {
+    /// The number of inserted numbers that have the prefix of this node.
+    count: usize,
+    /// The indices of the children for the digits `0` and `1`. The root is never a child so `0`
+    /// means there is no child.
+    children: [u32; 2],
+}
+
+/// A binary trie of numbers with the same amount of digits, starting from the most significant
+/// digit.
+///
+/// Each node keeps the amount of numbers with its prefix, so the numbers that pass a filter can
+/// be found by walking down the trie instead of scanning all the numbers for each digit.
+#[derive(Debug)]
+pub struct Trie {
+    nodes: Vec<Node>,
+    digits: usize,
+}
+
+impl Trie {
+    /// Build a trie with the digits of `numbers`.
+    pub fn new<B: Bits>(numbers: &[B], digits: usize) -> Self {
+        let mut nodes = vec![Node::default()];
+
+        for number in numbers {
+            let mut index = 0;
+            nodes[index].count += 1;
+
+            for pos in (0..digits).rev() {
+                let digit = usize::from(number.bit(pos));
+                let mut child = nodes[index].children[digit] as usize;
+
+                if child == 0 {
+                    child = nodes.len();
+                    nodes[index].children[digit] =
+                        u32::try_from(child).expect("too many nodes in trie");
+                    nodes.push(Node::default());
+                }
+
+                index = child;
+                nodes[index].count += 1;
+            }
+        }
+
+        Self { nodes, digits }
+    }
+
+    /// The number of numbers with the prefix of the child of `index` for `digit`.
+    fn count(&self, index: usize, digit: usize) -> usize {
+        match self.nodes[index].children[digit] {
+            0 => 0,
+            child => self.nodes[child as usize].count,
+        }
+    }
+
+    /// Find the only number that is left after filtering the numbers from the most significant
+    /// digit to the least one.
+    ///
+    /// `pick_bit` receives the amount of remaining numbers with a `1` in the current position
+    /// and the amount of remaining numbers, and returns the digit the numbers must have in that
+    /// position to be kept. Each position is recorded in `trace` if it is not `None`. This takes
+    /// `O(digits)` time.
+    pub fn filter<B: Bits>(
+        &self,
+        pick_bit: fn(usize, usize) -> bool,
+        mut trace: Option<&mut Trace>,
+    ) -> Result<B, RateError> {
+        let mut number = B::zero(self.digits);
+        let mut index = 0;
+        // Whether only one number is left.
+        let mut single = false;
+
+        for pos in (0..self.digits).rev() {
+            let node = &self.nodes[index];
+            let column = self.digits - 1 - pos;
+
+            let digit = if single {
+                // The rest of the digits are the ones of the only path left.
+                usize::from(node.children[1] != 0)
+            } else {
+                let ones_count = self.count(index, 1);
+                let picked_bit = pick_bit(ones_count, node.count);
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.record(column, ones_count, node.count, picked_bit);
+                }
+                usize::from(picked_bit)
+            };
+
+            // No numbers are left if there is no child for the picked digit.
+            index = match node.children[digit] {
+                0 => {
+                    return Err(RateError::NoneLeft {
+                        column,
+                        picked_digit: digit as u8,
+                    })
+                }
+                child => child as usize,
+            };
+
+            if digit == 1 {
+                number.set_bit(pos);
+            }
+
+            single = self.nodes[index].count == 1;
+        }
+
+        if single {
+            Ok(number)
+        } else {
+            Err(RateError::Duplicates {
+                remaining: self.nodes[index].count,
+            })
+        }
+    }
+}
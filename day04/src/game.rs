@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+
+use super::{Boards, Grid, WinRule};
+
+/// Something that happened during a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The number with index `draw_index` was drawn.
+    NumberDrawn { number: usize, draw_index: usize },
+    /// A board won.
+    BoardWon(Win),
+    /// Every board won or there are no numbers left.
+    GameOver,
+}
+
+/// A board that won a game. The first board to win has rank `1` and boards that win with the
+/// same number are ranked by their index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Win {
+    pub board: usize,
+    pub rank: usize,
+    pub draw_index: usize,
+    pub score: usize,
+}
+
+/// A bingo game that yields the events of each draw.
+#[derive(Debug)]
+pub struct Game<B> {
+    boards: Boards<B>,
+    numbers: Vec<usize>,
+    rules: Vec<Box<dyn WinRule>>,
+    /// Whether to scan every board for each number instead of using the index.
+    scan: bool,
+    /// The index of the next number to be drawn.
+    draw_index: usize,
+    /// The events of the last draw that have not been yielded yet.
+    events: VecDeque<Event>,
+    winners: Vec<Win>,
+    over: bool,
+}
+
+impl<B: Grid> Game<B> {
+    /// Create a game where `numbers` are drawn in order and the boards win following any of the
+    /// `rules`.
+    pub fn new(boards: Vec<B>, numbers: Vec<usize>, rules: Vec<Box<dyn WinRule>>) -> Self {
+        Self {
+            boards: Boards::new(boards),
+            numbers,
+            rules,
+            scan: false,
+            draw_index: 0,
+            events: VecDeque::new(),
+            winners: Vec::new(),
+            over: false,
+        }
+    }
+
+    /// Check every cell of every board for each number instead of using the index.
+    pub fn with_scan(mut self) -> Self {
+        self.scan = true;
+        self
+    }
+
+    /// Play until the first board wins and return it, if any board wins at all.
+    pub fn first_winner(&mut self) -> Option<Win> {
+        while self.winners.is_empty() && self.next().is_some() {}
+        self.winners.first().copied()
+    }
+
+    /// Play until the end and return the last board to win, if any board wins at all.
+    pub fn last_winner(&mut self) -> Option<Win> {
+        self.ranking().last().copied()
+    }
+
+    /// Play until the end and return every board that won, in the order they won.
+    pub fn ranking(&mut self) -> &[Win] {
+        self.by_ref().for_each(drop);
+        &self.winners
+    }
+
+    /// Draw the next number and queue its events.
+    fn draw(&mut self) {
+        let number = match self.numbers.get(self.draw_index) {
+            Some(&number) if self.winners.len() < self.boards.len() => number,
+            _ => {
+                self.events.push_back(Event::GameOver);
+                self.over = true;
+                return;
+            }
+        };
+        let draw_index = self.draw_index;
+        self.draw_index += 1;
+
+        self.events
+            .push_back(Event::NumberDrawn { number, draw_index });
+
+        let winners = if self.scan {
+            self.boards.scan(number, &self.rules)
+        } else {
+            self.boards.draw(number, &self.rules)
+        };
+
+        for board in winners {
+            let win = Win {
+                board,
+                rank: self.winners.len() + 1,
+                draw_index,
+                score: self.boards.get(board).unmarked_sum() * number,
+            };
+            self.winners.push(win);
+            self.events.push_back(Event::BoardWon(win));
+        }
+    }
+}
+
+impl<B: Grid> Iterator for Game<B> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.events.is_empty() && !self.over {
+            self.draw();
+        }
+        self.events.pop_front()
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use super::{Grid, WinRule};
+
+/// The number of marked cells in each row and column of a board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marks {
+    pub rows: Vec<usize>,
+    pub cols: Vec<usize>,
+}
+
+/// A set of boards with an index from each number to the cells that hold it.
+///
+/// Drawing a number only visits the cells with that number, so it takes time proportional to
+/// the amount of cells that hold it instead of to the amount of boards.
+#[derive(Debug)]
+pub struct Boards<B> {
+    boards: Vec<B>,
+    marks: Vec<Marks>,
+    /// Whether each board has already won.
+    won: Vec<bool>,
+    /// The board, row and column of the cells with each number, sorted by position.
+    positions: HashMap<usize, Vec<(usize, usize, usize)>>,
+    /// Whether the boards were marked with `scan`, which does not update the index.
+    scanned: bool,
+}
+
+impl<B: Grid> Boards<B> {
+    /// Build the index for `boards`. Cells that are already marked are counted in the marks of
+    /// their row and column but are not indexed because they cannot be marked again.
+    pub fn new(boards: Vec<B>) -> Self {
+        let mut positions = HashMap::<_, Vec<_>>::new();
+        let mut marks = Vec::with_capacity(boards.len());
+
+        for (index, board) in boards.iter().enumerate() {
+            let mut board_marks = Marks {
+                rows: vec![0; board.rows()],
+                cols: vec![0; board.cols()],
+            };
+
+            for i in 0..board.rows() {
+                for j in 0..board.cols() {
+                    let (cell, marked) = board.cell(i, j);
+                    if marked {
+                        board_marks.rows[i] += 1;
+                        board_marks.cols[j] += 1;
+                    } else {
+                        positions.entry(cell).or_default().push((index, i, j));
+                    }
+                }
+            }
+
+            marks.push(board_marks);
+        }
+
+        Self {
+            won: vec![false; boards.len()],
+            boards,
+            marks,
+            positions,
+            scanned: false,
+        }
+    }
+
+    /// The number of boards.
+    pub fn len(&self) -> usize {
+        self.boards.len()
+    }
+
+    /// The board with index `index`.
+    pub fn get(&self, index: usize) -> &B {
+        &self.boards[index]
+    }
+
+    /// Mark `number` in every board that has not won yet and return the indices of the boards
+    /// that won following any of the `rules`, in increasing order.
+    ///
+    /// Panics if `scan` was used before, because the index is out of date.
+    pub fn draw(&mut self, number: usize, rules: &[Box<dyn WinRule>]) -> Vec<usize> {
+        assert!(!self.scanned, "the index cannot be used after scanning");
+
+        let mut winners: Vec<usize> = Vec::new();
+        // The cells that were not marked this time and must be kept in the index.
+        let mut skipped = Vec::new();
+        // The last row where a cell was marked. Like `Grid::mark_number`, only one cell is marked
+        // in each row if a row has the same number more than once.
+        let mut last_row = None;
+
+        // Each cell can only be marked once so its position is not needed anymore.
+        for (index, i, j) in self.positions.remove(&number).unwrap_or_default() {
+            if self.won[index] {
+                continue;
+            }
+            if last_row == Some((index, i)) {
+                skipped.push((index, i, j));
+                continue;
+            }
+            last_row = Some((index, i));
+
+            let board = &mut self.boards[index];
+            board.cell_mut(i, j).1 = true;
+
+            let marks = &mut self.marks[index];
+            marks.rows[i] += 1;
+            marks.cols[j] += 1;
+
+            if rules
+                .iter()
+                .any(|rule| rule.check_marks(&*board, marks, i, j))
+                && winners.last() != Some(&index)
+            {
+                winners.push(index);
+            }
+        }
+
+        if !skipped.is_empty() {
+            self.positions.insert(number, skipped);
+        }
+
+        for &index in &winners {
+            self.won[index] = true;
+        }
+
+        winners
+    }
+
+    /// Same as `draw` but every cell of every board that has not won yet is checked instead of
+    /// using the index, so `draw` cannot be used afterwards.
+    pub fn scan(&mut self, number: usize, rules: &[Box<dyn WinRule>]) -> Vec<usize> {
+        self.scanned = true;
+        let mut winners = Vec::new();
+
+        for (index, board) in self.boards.iter_mut().enumerate() {
+            if !self.won[index] && board.mark_number(number, rules) {
+                self.won[index] = true;
+                winners.push(index);
+            }
+        }
+
+        winners
+    }
+}
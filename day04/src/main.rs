@@ -1,27 +1,41 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
+use game::{Event, Game};
+use index::{Boards, Marks};
+use rules::{Lines, Pattern, WinRule};
 
-/// The number of rows in each board.
-const ROWS: usize = 5;
-/// The number of columns in each board.
-const COLS: usize = 5;
+mod game;
+mod index;
+mod rules;
 
-/// A bingo board.
-#[derive(Debug)]
-struct Board {
-    inner: [[(usize, bool); COLS]; ROWS],
-}
+/// A grid of cells with a number and whether it has been marked.
+trait Grid {
+    /// The number of rows.
+    fn rows(&self) -> usize;
+
+    /// The number of columns.
+    fn cols(&self) -> usize;
+
+    /// The cell in the `i`th row and `j`th column.
+    fn cell(&self, i: usize, j: usize) -> (usize, bool);
 
-impl Board {
-    /// Mark a number in the board and return `true` if the board won or `false` otherwise.
-    fn mark_number(&mut self, number: usize) -> bool {
+    /// A mutable reference to the cell in the `i`th row and `j`th column.
+    fn cell_mut(&mut self, i: usize, j: usize) -> &mut (usize, bool);
+
+    /// Mark a number in the board and return `true` if the board won following any of the `rules`
+    /// or `false` otherwise.
+    fn mark_number(&mut self, number: usize, rules: &[Box<dyn WinRule>]) -> bool
+    where
+        Self: Sized,
+    {
         // Here we will store the position of the cells that were marked so they can be checked
         // later.
         let mut marked_cells = Vec::new();
 
-        for (i, row) in self.inner.iter_mut().enumerate() {
-            for (j, (cell, marked)) in row.iter_mut().enumerate() {
+        for i in 0..self.rows() {
+            for j in 0..self.cols() {
+                let (cell, marked) = self.cell_mut(i, j);
                 // If the current cell has the number and it has not been marked yet, mark it and
                 // push it to `marked_cells`.
                 if *cell == number && !*marked {
@@ -35,26 +49,17 @@ impl Board {
         // Check if any of the marked cells caused this board to win
         marked_cells
             .into_iter()
-            .any(|(i, j)| self.check_if_won(i, j))
-    }
-
-    /// Check if the current board won because of the `i`th row or the `j`th column.
-    fn check_if_won(&self, i: usize, j: usize) -> bool {
-        self.inner[i].iter().all(|(_, marked)| *marked)
-            || self
-                .inner
-                .iter()
-                .map(|row| row[j])
-                .all(|(_, marked)| marked)
+            .any(|(i, j)| rules.iter().any(|rule| rule.check(self, i, j)))
     }
 
     /// Compute the sum of the unmarked cells.
     fn unmarked_sum(&self) -> usize {
         let mut count = 0;
-        for row in &self.inner {
-            for (cell, marked) in row {
+        for i in 0..self.rows() {
+            for j in 0..self.cols() {
+                let (cell, marked) = self.cell(i, j);
                 if !marked {
-                    count += *cell;
+                    count += cell;
                 }
             }
         }
@@ -62,26 +67,305 @@ impl Board {
     }
 }
 
-impl FromStr for Board {
+/// A bingo board with `ROWS` rows and `COLS` columns known at compile time.
+#[derive(Debug)]
+struct Board<const ROWS: usize = 5, const COLS: usize = 5> {
+    inner: [[(usize, bool); COLS]; ROWS],
+}
+
+impl<const ROWS: usize, const COLS: usize> Grid for Board<ROWS, COLS> {
+    fn rows(&self) -> usize {
+        ROWS
+    }
+
+    fn cols(&self) -> usize {
+        COLS
+    }
+
+    fn cell(&self, i: usize, j: usize) -> (usize, bool) {
+        self.inner[i][j]
+    }
+
+    fn cell_mut(&mut self, i: usize, j: usize) -> &mut (usize, bool) {
+        &mut self.inner[i][j]
+    }
+}
+
+impl<const ROWS: usize, const COLS: usize> TryFrom<DynBoard> for Board<ROWS, COLS> {
+    type Error = ShapeError;
+
+    fn try_from(board: DynBoard) -> Result<Self, Self::Error> {
+        if (board.rows, board.cols) != (ROWS, COLS) {
+            return Err(ShapeError::Mismatch {
+                board: None,
+                expected: (ROWS, COLS),
+                found: (board.rows, board.cols),
+            });
+        }
+
+        let mut inner = [[(0usize, false); COLS]; ROWS];
+        for (row, cells) in inner.iter_mut().zip(board.cells.chunks_exact(COLS)) {
+            row.copy_from_slice(cells);
+        }
+
+        Ok(Self { inner })
+    }
+}
+
+impl<const ROWS: usize, const COLS: usize> FromStr for Board<ROWS, COLS> {
     type Err = Error;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let mut inner = [[(0usize, false); COLS]; ROWS];
-        let mut lines = input.lines();
+        Ok(input.parse::<DynBoard>()?.try_into()?)
+    }
+}
+
+/// A bingo board with dimensions known at runtime.
+#[derive(Debug)]
+struct DynBoard {
+    rows: usize,
+    cols: usize,
+    /// The cells of each row, one row after another.
+    cells: Vec<(usize, bool)>,
+}
+
+impl Grid for DynBoard {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn cell(&self, i: usize, j: usize) -> (usize, bool) {
+        self.cells[i * self.cols + j]
+    }
+
+    fn cell_mut(&mut self, i: usize, j: usize) -> &mut (usize, bool) {
+        &mut self.cells[i * self.cols + j]
+    }
+}
 
-        for row in &mut inner {
+impl FromStr for DynBoard {
+    type Err = Error;
+
+    /// Parse a board, taking its number of columns from the first row.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut cols = None;
+        let mut cells = Vec::new();
+        let mut rows = 0;
+
+        for line in input.lines() {
             // Split each line of the input by whitespaces and parse each chunk as an integer.
-            let mut nums = lines.next().context("missing row")?.split_whitespace();
-            for (cell, _) in row.iter_mut() {
-                *cell = nums.next().context("missing number")?.parse()?;
+            let start = cells.len();
+            for num in line.split_whitespace() {
+                cells.push((num.parse()?, false));
             }
+
+            let len = cells.len() - start;
+            let expected = *cols.get_or_insert(len);
+            if len != expected {
+                return Err(ShapeError::Ragged {
+                    row: rows,
+                    len,
+                    expected,
+                }
+                .into());
+            }
+
+            rows += 1;
         }
 
-        Ok(Self { inner })
+        match cols {
+            Some(cols) if cols > 0 => Ok(Self { rows, cols, cells }),
+            _ => Err(ShapeError::Empty.into()),
+        }
+    }
+}
+
+/// Error returned when a board does not have the expected dimensions.
+#[derive(Debug, PartialEq, Eq)]
+enum ShapeError {
+    /// The board does not have any numbers.
+    Empty,
+    /// A row of the board has a different number of columns than the first one.
+    Ragged {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    /// The board does not have the same number of rows and columns as expected.
+    Mismatch {
+        /// The index of the board in the input, if any.
+        board: Option<usize>,
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the board is empty"),
+            Self::Ragged { row, len, expected } => write!(
+                f,
+                "row {} has {} numbers but the first row has {}",
+                row, len, expected
+            ),
+            Self::Mismatch {
+                board,
+                expected,
+                found,
+            } => {
+                if let Some(board) = board {
+                    write!(f, "board {} ", board)?;
+                } else {
+                    write!(f, "the board ")?;
+                }
+                write!(
+                    f,
+                    "is {}x{} but it should be {}x{}",
+                    found.0, found.1, expected.0, expected.1
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+/// Parse the boards separated by empty lines. The dimensions of every board must be the same as
+/// the ones of the first board.
+fn parse_boards(input: &str) -> Result<Vec<DynBoard>, Error> {
+    let mut boards = Vec::<DynBoard>::new();
+
+    for (index, s) in input.trim_end().split("\n\n").enumerate() {
+        let board: DynBoard = s
+            .parse()
+            .with_context(|| format!("could not parse board {}", index))?;
+
+        if let Some(first) = boards.first() {
+            if (board.rows, board.cols) != (first.rows, first.cols) {
+                return Err(ShapeError::Mismatch {
+                    board: Some(index),
+                    expected: (first.rows, first.cols),
+                    found: (board.rows, board.cols),
+                }
+                .into());
+            }
+        }
+
+        boards.push(board);
+    }
+
+    Ok(boards)
+}
+
+/// What to print about a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    /// The score of each board when it wins.
+    Scores,
+    /// The first and last boards to win.
+    Summary,
+    /// Every board that won, in the order they won.
+    Ranking,
+}
+
+/// Play a game where the boards win following any of the `rules` and print its results.
+///
+/// The boards are marked using an index unless `scan` is `true`, in which case every cell of
+/// every board is checked for each number.
+fn play<B: Grid>(
+    numbers: Vec<usize>,
+    boards: Vec<B>,
+    rules: Vec<Box<dyn WinRule>>,
+    scan: bool,
+    output: Output,
+) {
+    let mut game = Game::new(boards, numbers, rules);
+    if scan {
+        game = game.with_scan();
+    }
+
+    match output {
+        Output::Scores => {
+            for event in game {
+                if let Event::BoardWon(win) = event {
+                    println!("Board won: {}", win.score);
+                }
+            }
+        }
+        Output::Summary => {
+            let first = game.first_winner();
+            let last = game.last_winner();
+            for (name, win) in [("First", first), ("Last", last)] {
+                match win {
+                    Some(win) => println!(
+                        "{} winner: board {} with score {}",
+                        name, win.board, win.score
+                    ),
+                    None => println!("{} winner: none", name),
+                }
+            }
+        }
+        Output::Ranking => {
+            println!("{:>6}{:>8}{:>8}{:>10}", "rank", "board", "draw", "score");
+            for win in game.ranking() {
+                println!(
+                    "{:>6}{:>8}{:>8}{:>10}",
+                    win.rank, win.board, win.draw_index, win.score
+                );
+            }
+        }
     }
 }
 
 fn main() -> Result<(), Error> {
+    let mut rules = Vec::new();
+    let mut patterns = Vec::new();
+    let mut scan = false;
+    let mut output = Output::Scores;
+
+    // `--rules=<rule>,...` picks the built-in rules used to win and `--pattern=<mask>` adds a
+    // custom pattern, with rows separated by `/`. The boards win by completing lines otherwise.
+    // `--scan` checks every cell of every board for each number instead of using an index.
+    // `--summary` and `--ranking` print the first and last winners or every winner instead of
+    // the score of each board when it wins.
+    for arg in std::env::args().skip(1) {
+        if let Some(value) = arg.strip_prefix("--rules=") {
+            for name in value.split(',') {
+                rules.push(rules::parse_rule(name)?);
+            }
+        } else if let Some(value) = arg.strip_prefix("--pattern=") {
+            patterns.push(value.parse::<Pattern>()?);
+        } else if arg == "--scan" {
+            scan = true;
+        } else if arg == "--summary" {
+            output = Output::Summary;
+        } else if arg == "--ranking" {
+            output = Output::Ranking;
+        } else {
+            bail!("unexpected argument {:?}", arg);
+        }
+    }
+
+    // The patterns must have the same dimensions as each other, even if there are no boards.
+    if let Some(first) = patterns.first() {
+        for pattern in &patterns[1..] {
+            if (pattern.rows, pattern.cols) != (first.rows, first.cols) {
+                bail!(
+                    "the pattern is {}x{} but the first one is {}x{}",
+                    pattern.rows,
+                    pattern.cols,
+                    first.rows,
+                    first.cols
+                );
+            }
+        }
+    }
+
     let input = std::fs::read_to_string("./input").context("could not read input file")?;
 
     // `head` has the numbers to be drawn and `tail` the boards.
@@ -95,34 +379,295 @@ fn main() -> Result<(), Error> {
         .context("could not parse numbers to be drawn")?;
 
     // Parse the boards splitting `tail` every two new lines.
-    let mut boards = tail
-        .split("\n\n")
-        .map(|s| s.parse())
-        .collect::<Result<Vec<Board>, _>>()
-        .context("could not parse boards")?;
-
-    for number in numbers {
-        // Here we store the indices of the boards that must be deleted because they already won.
-        let mut indices_to_delete = Vec::new();
-
-        for (index, board) in boards.iter_mut().enumerate() {
-            // If the board won while drawing this number, we print its score and then add it to
-            // the deletion buffer.
-            if board.mark_number(number) {
-                println!("Board won: {}", board.unmarked_sum() * number);
-                // We add it in order because deletion must be done starting with the largest index
-                // first.
-                if let Err(i) = indices_to_delete.binary_search(&index) {
-                    indices_to_delete.insert(i, index);
+    let boards = parse_boards(tail).context("could not parse boards")?;
+
+    // The patterns must also have the same dimensions as the boards.
+    if let (Some(pattern), Some(board)) = (patterns.first(), boards.first()) {
+        if (pattern.rows, pattern.cols) != (board.rows, board.cols) {
+            bail!(
+                "the pattern is {}x{} but the boards are {}x{}",
+                pattern.rows,
+                pattern.cols,
+                board.rows,
+                board.cols
+            );
+        }
+    }
+    for pattern in patterns {
+        rules.push(Box::new(pattern));
+    }
+
+    if rules.is_empty() {
+        rules.push(Box::new(Lines));
+    }
+
+    // Use boards with fixed dimensions for the usual 5x5 boards.
+    if boards
+        .first()
+        .is_some_and(|board| (board.rows, board.cols) == (5, 5))
+    {
+        let boards = boards
+            .into_iter()
+            .map(Board::<5, 5>::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        play(numbers, boards, rules, scan, output);
+    } else {
+        play(numbers, boards, rules, scan, output);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimensions() {
+        let lines: Vec<Box<dyn WinRule>> = vec![Box::new(Lines)];
+
+        let input = "1 2 3\n4 5 6\n7 8 9\n\n9 8 7\n6 5 4\n3 2 1\n";
+        let mut boards = parse_boards(input).unwrap();
+        assert_eq!(2, boards.len());
+        assert_eq!((3, 3), (boards[1].rows(), boards[1].cols()));
+
+        assert!(!boards[1].mark_number(5, &lines));
+        assert!(!boards[1].mark_number(2, &lines));
+        assert!(boards[1].mark_number(8, &lines));
+        assert_eq!(45 - 15, boards[1].unmarked_sum());
+
+        let mut board: Board<3, 3> = "1 2 3\n4 5 6\n7 8 9".parse().unwrap();
+        assert!(!board.mark_number(3, &lines));
+        assert!(!board.mark_number(6, &lines));
+        assert!(board.mark_number(9, &lines));
+        assert_eq!(45 - 18, board.unmarked_sum());
+
+        // Rectangular boards are fine as long as all of them have the same dimensions.
+        let input = "1 2 3 4\n5 6 7 8\n\n8 7 6 5\n4 3 2 1";
+        let boards = parse_boards(input).unwrap();
+        assert_eq!((2, 4), (boards[0].rows(), boards[0].cols()));
+
+        let input = "1 2 3\n4 5 6\n7 8 9\n\n1 2\n3 4";
+        let error = parse_boards(input).unwrap_err();
+        assert_eq!(
+            Some(&ShapeError::Mismatch {
+                board: Some(1),
+                expected: (3, 3),
+                found: (2, 2)
+            }),
+            error.downcast_ref()
+        );
+
+        let error = "1 2 3\n4 5\n7 8 9".parse::<DynBoard>().unwrap_err();
+        assert_eq!(
+            Some(&ShapeError::Ragged {
+                row: 1,
+                len: 2,
+                expected: 3
+            }),
+            error.downcast_ref()
+        );
+
+        assert!("1 2 3\n4 5 6".parse::<Board>().is_err());
+    }
+
+    #[test]
+    fn rules() {
+        let input = "1 2 3\n4 5 6\n7 8 9";
+        let mark = |numbers: &[usize], rules: Vec<Box<dyn WinRule>>| {
+            let mut board: Board<3, 3> = input.parse().unwrap();
+            numbers
+                .iter()
+                .map(|&number| board.mark_number(number, &rules))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![false, false, true],
+            mark(&[1, 5, 9], vec![Box::new(rules::Diagonals)])
+        );
+        assert_eq!(
+            vec![false, false, false],
+            mark(&[1, 5, 9], vec![Box::new(Lines)])
+        );
+        assert_eq!(
+            vec![false, false, false, false, true],
+            mark(&[1, 5, 9, 3, 7], vec![Box::new(rules::Cross)])
+        );
+        assert_eq!(
+            vec![false, false, false, true],
+            mark(&[1, 3, 7, 9], vec![Box::new(rules::Corners)])
+        );
+        // Any of the rules can be used to win.
+        assert_eq!(
+            vec![false, false, true],
+            mark(&[2, 5, 8], vec![Box::new(rules::Corners), Box::new(Lines)])
+        );
+
+        let numbers = (1..=9).collect::<Vec<_>>();
+        let mut expected = vec![false; 9];
+        expected[8] = true;
+        assert_eq!(expected, mark(&numbers, vec![Box::new(rules::Blackout)]));
+
+        let pattern: Pattern = ".#./###/.#.".parse().unwrap();
+        assert_eq!(pattern, ".#.\n###\n.#.\n".parse().unwrap());
+        assert_eq!(
+            vec![false, false, false, false, false, true],
+            mark(&[1, 2, 4, 5, 6, 8], vec![Box::new(pattern)])
+        );
+
+        assert!("#.#/##".parse::<Pattern>().is_err());
+        assert!("#.#/.x.".parse::<Pattern>().is_err());
+        assert!(".../...".parse::<Pattern>().is_err());
+        assert!(rules::parse_rule("triangle").is_err());
+    }
+
+    #[test]
+    fn index() {
+        let boards = "22 13 17 11  0\n 8  2 23  4 24\n21  9 14 16  7\n 6 10  3 18  5\n 1 12 20 15 19\n\n 3 15  0  2 22\n 9 18 13 17  5\n19  8  7 25 23\n20 11 10 24  4\n14 21 16 12  6\n\n14 21 17 24  4\n10 16 15  9 19\n18  8 23 26 20\n22 11 13  6  5\n 2  0 12  3  7";
+        let numbers = [
+            7, 4, 9, 5, 11, 17, 23, 2, 0, 14, 21, 24, 10, 16, 13, 6, 15, 25, 12, 22, 18, 20, 8, 19,
+            3, 26, 1,
+        ];
+
+        for rules in [
+            vec![Box::new(Lines) as Box<dyn WinRule>],
+            vec![Box::new(rules::Blackout)],
+        ] {
+            // Mark every board that has not won yet by scanning its cells.
+            let mut scanned = parse_boards(boards).unwrap();
+            let mut won = vec![false; scanned.len()];
+            let mut expected = Vec::new();
+            for number in numbers {
+                for (index, board) in scanned.iter_mut().enumerate() {
+                    if !won[index] && board.mark_number(number, &rules) {
+                        won[index] = true;
+                        expected.push((index, board.unmarked_sum() * number));
+                    }
                 }
             }
-        }
 
-        // Delete the winning boards starting from the largest index to avoid index shifting.
-        for index in indices_to_delete.into_iter().rev() {
-            boards.remove(index);
+            let mut indexed = Boards::new(parse_boards(boards).unwrap());
+            let mut winners = Vec::new();
+            for number in numbers {
+                for index in indexed.draw(number, &rules) {
+                    winners.push((index, indexed.get(index).unmarked_sum() * number));
+                }
+            }
+
+            assert_eq!(expected, winners);
         }
+
+        // Only one cell is marked in each row for each draw, like when scanning.
+        let mut boards = Boards::new(vec!["1 1\n2 3".parse::<DynBoard>().unwrap()]);
+        let lines = vec![Box::new(Lines) as Box<dyn WinRule>];
+        assert!(boards.draw(1, &lines).is_empty());
+        assert_eq!(vec![0], boards.draw(1, &lines));
+
+        // Cells marked before building the index count towards their row and column.
+        let mut board = "1 2\n3 4".parse::<DynBoard>().unwrap();
+        assert!(!board.mark_number(1, &lines));
+        let mut boards = Boards::new(vec![board]);
+        assert!(boards.draw(4, &lines).is_empty());
+        assert_eq!(vec![0], boards.draw(2, &lines));
     }
 
-    Ok(())
+    #[test]
+    #[should_panic(expected = "the index cannot be used after scanning")]
+    fn draw_after_scan() {
+        let mut boards = Boards::new(vec!["1 2\n3 4".parse::<DynBoard>().unwrap()]);
+        let lines = vec![Box::new(Lines) as Box<dyn WinRule>];
+        boards.scan(1, &lines);
+        boards.draw(2, &lines);
+    }
+
+    #[test]
+    fn game() {
+        use game::Win;
+
+        let boards = "22 13 17 11  0\n 8  2 23  4 24\n21  9 14 16  7\n 6 10  3 18  5\n 1 12 20 15 19\n\n 3 15  0  2 22\n 9 18 13 17  5\n19  8  7 25 23\n20 11 10 24  4\n14 21 16 12  6\n\n14 21 17 24  4\n10 16 15  9 19\n18  8 23 26 20\n22 11 13  6  5\n 2  0 12  3  7";
+        let numbers = vec![
+            7, 4, 9, 5, 11, 17, 23, 2, 0, 14, 21, 24, 10, 16, 13, 6, 15, 25, 12, 22, 18, 20, 8, 19,
+            3, 26, 1,
+        ];
+        let game = || {
+            let boards = parse_boards(boards).unwrap();
+            Game::new(boards, numbers.clone(), vec![Box::new(Lines)])
+        };
+
+        let events = game().collect::<Vec<_>>();
+        assert_eq!(
+            Some(&Event::NumberDrawn {
+                number: 7,
+                draw_index: 0
+            }),
+            events.first()
+        );
+        assert_eq!(
+            vec![
+                Event::BoardWon(Win {
+                    board: 2,
+                    rank: 1,
+                    draw_index: 11,
+                    score: 4512
+                }),
+                Event::BoardWon(Win {
+                    board: 0,
+                    rank: 2,
+                    draw_index: 13,
+                    score: 2192
+                }),
+                Event::BoardWon(Win {
+                    board: 1,
+                    rank: 3,
+                    draw_index: 14,
+                    score: 1924
+                }),
+            ],
+            events
+                .iter()
+                .copied()
+                .filter(|event| matches!(event, Event::BoardWon(_)))
+                .collect::<Vec<_>>()
+        );
+        // The game is over as soon as every board won.
+        assert_eq!(
+            &[
+                Event::NumberDrawn {
+                    number: 13,
+                    draw_index: 14
+                },
+                Event::BoardWon(Win {
+                    board: 1,
+                    rank: 3,
+                    draw_index: 14,
+                    score: 1924
+                }),
+                Event::GameOver
+            ],
+            &events[events.len() - 3..]
+        );
+
+        assert_eq!(Some(4512), game().first_winner().map(|win| win.score));
+        assert_eq!(Some(1924), game().last_winner().map(|win| win.score));
+        let ranking = game()
+            .ranking()
+            .iter()
+            .map(|win| win.board)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![2, 0, 1], ranking);
+        assert_eq!(
+            game().collect::<Vec<_>>(),
+            game().with_scan().collect::<Vec<_>>()
+        );
+
+        // Nobody wins if the numbers run out.
+        let mut game = Game::new(
+            parse_boards(boards).unwrap(),
+            vec![7, 4],
+            vec![Box::new(Lines)],
+        );
+        assert_eq!(None, game.first_winner());
+        assert_eq!(None, game.next());
+    }
 }
@@ -0,0 +1,183 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Error};
+
+use super::{Grid, Marks};
+
+/// A way of winning a bingo game.
+pub trait WinRule: fmt::Debug {
+    /// Check if `board` won after marking the cell in the `i`th row and `j`th column.
+    fn check(&self, board: &dyn Grid, i: usize, j: usize) -> bool;
+
+    /// Same as `check` but `marks` has the number of marked cells in each row and column of
+    /// `board`, which some rules use to avoid visiting the cells.
+    fn check_marks(&self, board: &dyn Grid, _marks: &Marks, i: usize, j: usize) -> bool {
+        self.check(board, i, j)
+    }
+}
+
+/// Check if every cell in `cells` is marked.
+fn all_marked(board: &dyn Grid, mut cells: impl Iterator<Item = (usize, usize)>) -> bool {
+    cells.all(|(i, j)| board.cell(i, j).1)
+}
+
+/// Win by marking a whole row or column.
+#[derive(Debug)]
+pub struct Lines;
+
+impl WinRule for Lines {
+    fn check(&self, board: &dyn Grid, i: usize, j: usize) -> bool {
+        // Only the row and the column of the marked cell could have been completed.
+        all_marked(board, (0..board.cols()).map(|j| (i, j)))
+            || all_marked(board, (0..board.rows()).map(|i| (i, j)))
+    }
+
+    fn check_marks(&self, board: &dyn Grid, marks: &Marks, i: usize, j: usize) -> bool {
+        marks.rows[i] == board.cols() || marks.cols[j] == board.rows()
+    }
+}
+
+/// The cells of the diagonal from the top left corner and of the one from the top right corner,
+/// or `None` if the board is not square.
+fn diagonals(board: &dyn Grid) -> Option<[Vec<(usize, usize)>; 2]> {
+    let n = board.rows();
+    if n != board.cols() {
+        return None;
+    }
+
+    Some([
+        (0..n).map(|k| (k, k)).collect(),
+        (0..n).map(|k| (k, n - 1 - k)).collect(),
+    ])
+}
+
+/// Win by marking one of the diagonals of a square board.
+#[derive(Debug)]
+pub struct Diagonals;
+
+impl WinRule for Diagonals {
+    fn check(&self, board: &dyn Grid, _i: usize, _j: usize) -> bool {
+        diagonals(board).is_some_and(|diagonals| {
+            diagonals
+                .into_iter()
+                .any(|diagonal| all_marked(board, diagonal.into_iter()))
+        })
+    }
+}
+
+/// Win by marking both diagonals of a square board.
+#[derive(Debug)]
+pub struct Cross;
+
+impl WinRule for Cross {
+    fn check(&self, board: &dyn Grid, _i: usize, _j: usize) -> bool {
+        diagonals(board).is_some_and(|diagonals| {
+            diagonals
+                .into_iter()
+                .all(|diagonal| all_marked(board, diagonal.into_iter()))
+        })
+    }
+}
+
+/// Win by marking the four corners.
+#[derive(Debug)]
+pub struct Corners;
+
+impl WinRule for Corners {
+    fn check(&self, board: &dyn Grid, _i: usize, _j: usize) -> bool {
+        let (last_row, last_col) = (board.rows() - 1, board.cols() - 1);
+        let corners = [(0, 0), (0, last_col), (last_row, 0), (last_row, last_col)];
+        all_marked(board, corners.into_iter())
+    }
+}
+
+/// Win by marking every cell.
+#[derive(Debug)]
+pub struct Blackout;
+
+impl WinRule for Blackout {
+    fn check(&self, board: &dyn Grid, _i: usize, _j: usize) -> bool {
+        all_marked(
+            board,
+            (0..board.rows()).flat_map(|i| (0..board.cols()).map(move |j| (i, j))),
+        )
+    }
+
+    fn check_marks(&self, board: &dyn Grid, marks: &Marks, _i: usize, _j: usize) -> bool {
+        marks.rows.iter().sum::<usize>() == board.rows() * board.cols()
+    }
+}
+
+/// Win by marking the cells of a custom pattern.
+///
+/// A pattern is written as one line per row, or with rows separated by `/`, where `#` is a cell
+/// that must be marked and `.` is any other cell. The pattern must have the same dimensions as
+/// the boards.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub rows: usize,
+    pub cols: usize,
+    /// The position of the cells that must be marked.
+    cells: Vec<(usize, usize)>,
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut cols = None;
+        let mut cells = Vec::new();
+        let mut rows = 0;
+
+        for line in input.split(['\n', '/']) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let expected = *cols.get_or_insert(line.len());
+            if line.len() != expected {
+                bail!(
+                    "pattern row {} has {} cells instead of {}",
+                    rows,
+                    line.len(),
+                    expected
+                );
+            }
+
+            for (j, c) in line.chars().enumerate() {
+                match c {
+                    '#' => cells.push((rows, j)),
+                    '.' => {}
+                    _ => bail!("invalid character {:?} in pattern", c),
+                }
+            }
+
+            rows += 1;
+        }
+
+        match cols {
+            Some(cols) if !cells.is_empty() => Ok(Self { rows, cols, cells }),
+            _ => bail!("the pattern does not have any cells to mark"),
+        }
+    }
+}
+
+impl WinRule for Pattern {
+    fn check(&self, board: &dyn Grid, _i: usize, _j: usize) -> bool {
+        (self.rows, self.cols) == (board.rows(), board.cols())
+            && all_marked(board, self.cells.iter().copied())
+    }
+}
+
+/// Parse the name of a built-in rule.
+pub fn parse_rule(name: &str) -> Result<Box<dyn WinRule>, Error> {
+    match name {
+        "lines" => Ok(Box::new(Lines)),
+        "diagonals" => Ok(Box::new(Diagonals)),
+        "x" => Ok(Box::new(Cross)),
+        "corners" => Ok(Box::new(Corners)),
+        "blackout" => Ok(Box::new(Blackout)),
+        _ => bail!("unknown rule {:?}", name),
+    }
+}